@@ -0,0 +1,95 @@
+use crate::TasksError;
+
+const FLAG_PREFIX: &str = "--";
+
+/// One command-line argument: a `--flag`, with its value when written as
+/// `--flag=value`, or a plain value.
+pub enum Arg<'a> {
+    Flag(&'a str, Option<&'a str>),
+    Value(&'a str),
+}
+
+/// Walks command-line arguments. After a bare `--`, everything is a value,
+/// so task names may start with dashes.
+pub struct Args<'a> {
+    rest: &'a [String],
+    only_values: bool,
+}
+
+impl<'a> Args<'a> {
+    pub fn new(args: &'a [String]) -> Self {
+        Self { rest: args, only_values: false }
+    }
+
+    /// The arguments not consumed yet.
+    pub fn rest(&self) -> &'a [String] {
+        self.rest
+    }
+
+    /// Whether the next argument is a flag.
+    pub fn at_flag(&self) -> bool {
+        !self.only_values
+            && self.rest.first().is_some_and(|arg| arg.starts_with(FLAG_PREFIX) && arg != FLAG_PREFIX)
+    }
+
+    /// Whether the next argument is exactly `value`, such as a short flag.
+    pub fn at(&self, value: &str) -> bool {
+        !self.only_values && self.rest.first().is_some_and(|arg| arg == value)
+    }
+
+    /// Takes the value of `flag`, either given inline or as the next argument.
+    pub fn value(&mut self, flag: &str, inline: Option<&'a str>) -> Result<&'a str, TasksError> {
+        if let Some(value) = inline {
+            return Ok(value);
+        }
+
+        let (value, rest) = self
+            .rest
+            .split_first()
+            .ok_or_else(|| TasksError::InvalidArgument(format!("Missing value for {flag}")))?;
+        self.rest = rest;
+
+        Ok(value)
+    }
+
+    /// Takes the next plain value, failing with `MissingArgument(what)`.
+    pub fn required(&mut self, what: &'static str) -> Result<&'a str, TasksError> {
+        match self.next() {
+            Some(Arg::Value(value)) => Ok(value),
+            Some(Arg::Flag(flag, _)) => Err(TasksError::UnknownFlag(flag.to_string())),
+            None => Err(TasksError::MissingArgument(what)),
+        }
+    }
+
+    /// Fails if any argument is left.
+    pub fn finish(mut self) -> Result<(), TasksError> {
+        match self.next() {
+            Some(Arg::Value(value)) => Err(TasksError::InvalidArgument(format!("Unexpected argument {value:?}"))),
+            Some(Arg::Flag(flag, _)) => Err(TasksError::UnknownFlag(flag.to_string())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> Iterator for Args<'a> {
+    type Item = Arg<'a>;
+
+    fn next(&mut self) -> Option<Arg<'a>> {
+        let (arg, rest) = self.rest.split_first()?;
+        self.rest = rest;
+
+        if self.only_values || !arg.starts_with(FLAG_PREFIX) {
+            return Some(Arg::Value(arg));
+        }
+
+        if arg == FLAG_PREFIX {
+            self.only_values = true;
+            return self.next();
+        }
+
+        Some(match arg.split_once('=') {
+            Some((flag, value)) => Arg::Flag(flag, Some(value)),
+            None => Arg::Flag(arg, None),
+        })
+    }
+}
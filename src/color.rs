@@ -0,0 +1,159 @@
+//! Terminal colors for `list`, turned on by `--color` or when printing to a
+//! terminal, and styled by a theme that the config file can change.
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+
+use crate::config::{
+    COLOR_COMPLETED, COLOR_OVERDUE, COLOR_PRIORITY_HIGH, COLOR_PRIORITY_LOW, COLOR_PRIORITY_MEDIUM, COLOR_TAG, COLOR_TODAY,
+};
+use crate::{Config, Priority, TasksError};
+
+/// Set to any non-empty value to turn colors off unless asked for.
+const NO_COLOR: &str = "NO_COLOR";
+
+/// Whether output is colored.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorMode {
+    /// Colored when standard output is a terminal and `NO_COLOR` is unset.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                io::stdout().is_terminal() && env::var_os(NO_COLOR).is_none_or(|value| value.is_empty())
+            },
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = TasksError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            _ => Err(TasksError::InvalidArgument(format!("Invalid color mode {value:?}, expected always, never or auto"))),
+        }
+    }
+}
+
+/// How a piece of text is shown, as ANSI SGR codes; empty for plain text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Style {
+    codes: Vec<u8>,
+}
+
+impl Style {
+    /// Wraps `text` in the escape sequences for this style.
+    pub fn paint(&self, text: &str) -> String {
+        if self.codes.is_empty() || text.is_empty() {
+            return text.to_string();
+        }
+
+        let codes: Vec<String> = self.codes.iter().map(u8::to_string).collect();
+        format!("\x1b[{}m{text}\x1b[0m", codes.join(";"))
+    }
+}
+
+/// Parses space-separated words: `bold`, `dim`, `italic`, `underline`, a
+/// color (`black`, `red`, `green`, `yellow`, `blue`, `magenta`, `cyan`,
+/// `white`), optionally `bright-` or `on-` (background), or `plain` alone.
+impl FromStr for Style {
+    type Err = TasksError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        const COLORS: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+        let color = |name: &str| COLORS.iter().position(|color| *color == name).map(|index| index as u8);
+
+        let mut codes = Vec::new();
+        for word in value.to_lowercase().split_whitespace() {
+            let code = match word {
+                "plain" => continue,
+                "bold" => Some(1),
+                "dim" => Some(2),
+                "italic" => Some(3),
+                "underline" => Some(4),
+                _ => match (word.strip_prefix("bright-"), word.strip_prefix("on-")) {
+                    (Some(name), _) => color(name).map(|index| 90 + index),
+                    (_, Some(name)) => color(name).map(|index| 40 + index),
+                    _ => color(word).map(|index| 30 + index),
+                },
+            };
+
+            codes.push(code.ok_or_else(|| {
+                TasksError::InvalidArgument(format!(
+                    "Invalid style {value:?}, expected plain or words such as bold, dim, red, bright-blue or on-yellow"
+                ))
+            })?);
+        }
+
+        Ok(Self { codes })
+    }
+}
+
+/// The styles `list` uses. Each can be set in the config file, e.g.
+/// `color.overdue = bold red`.
+#[derive(Default)]
+pub struct Theme {
+    /// The whole row of a completed task.
+    pub completed: Style,
+    /// The due date of a pending task past it.
+    pub overdue: Style,
+    /// The due date of a pending task due today.
+    pub today: Style,
+    pub priority_high: Style,
+    pub priority_medium: Style,
+    pub priority_low: Style,
+    pub tag: Style,
+}
+
+impl Theme {
+    /// The built-in theme with the settings in `config` applied over it.
+    pub fn load(config: &Config) -> Result<Self, TasksError> {
+        let mut theme = Self {
+            completed: "dim".parse()?,
+            overdue: "red".parse()?,
+            today: "yellow".parse()?,
+            priority_high: "bold red".parse()?,
+            priority_medium: "yellow".parse()?,
+            priority_low: "blue".parse()?,
+            tag: "cyan".parse()?,
+        };
+
+        let styles = [
+            (COLOR_COMPLETED, &mut theme.completed),
+            (COLOR_OVERDUE, &mut theme.overdue),
+            (COLOR_TODAY, &mut theme.today),
+            (COLOR_PRIORITY_HIGH, &mut theme.priority_high),
+            (COLOR_PRIORITY_MEDIUM, &mut theme.priority_medium),
+            (COLOR_PRIORITY_LOW, &mut theme.priority_low),
+            (COLOR_TAG, &mut theme.tag),
+        ];
+        for (key, style) in styles {
+            if let Some(value) = config.get(key) {
+                *style = value.parse()?;
+            }
+        }
+
+        Ok(theme)
+    }
+
+    pub fn priority(&self, priority: Priority) -> &Style {
+        match priority {
+            Priority::High => &self.priority_high,
+            Priority::Medium => &self.priority_medium,
+            Priority::Low => &self.priority_low,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Settings kept per database in a `key = value` file next to it, such as
+//! `tasks.txt.config`. Lines starting with `#` are comments, and are kept
+//! when settings are changed.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::color::Style;
+use crate::list::Column;
+use crate::store::file::write_atomically;
+use crate::task::parse_list;
+use crate::{dates, SystemClock, TasksError};
+
+pub(crate) const CONFIG_SUFFIX: &str = ".config";
+const COMMENT_PREFIX: char = '#';
+
+/// Known keys with a description of their values.
+pub const KEYS: &[(&str, &str)] = &[
+    (ARCHIVE_AFTER, "archive completed tasks older than this age, e.g. 30d"),
+    (LIST_DEFAULT, "the list used when no -l is given, e.g. work"),
+    (LIST_COLUMNS, "the columns list shows, e.g. id,status,due,task"),
+    (COLOR_COMPLETED, "the style of completed tasks, e.g. dim"),
+    (COLOR_OVERDUE, "the style of overdue due dates, e.g. bold red"),
+    (COLOR_TODAY, "the style of due dates today, e.g. yellow"),
+    (COLOR_PRIORITY_HIGH, "the style of priority H, e.g. bold red"),
+    (COLOR_PRIORITY_MEDIUM, "the style of priority M, e.g. yellow"),
+    (COLOR_PRIORITY_LOW, "the style of priority L, e.g. blue"),
+    (COLOR_TAG, "the style of tags, e.g. cyan"),
+];
+
+pub const ARCHIVE_AFTER: &str = "archive.after";
+pub const LIST_DEFAULT: &str = "list.default";
+pub const LIST_COLUMNS: &str = "list.columns";
+pub const COLOR_COMPLETED: &str = "color.completed";
+pub const COLOR_OVERDUE: &str = "color.overdue";
+pub const COLOR_TODAY: &str = "color.today";
+pub const COLOR_PRIORITY_HIGH: &str = "color.priority.high";
+pub const COLOR_PRIORITY_MEDIUM: &str = "color.priority.medium";
+pub const COLOR_PRIORITY_LOW: &str = "color.priority.low";
+pub const COLOR_TAG: &str = "color.tag";
+
+pub struct Config {
+    /// Where the settings are saved; without one they cannot be changed.
+    path: Option<PathBuf>,
+    values: BTreeMap<String, String>,
+    /// The lines of the file as read, comments included.
+    lines: Vec<String>,
+}
+
+impl Config {
+    /// Reads the settings at `path`. A missing file has no settings.
+    pub fn load(path: Option<PathBuf>) -> Result<Self, TasksError> {
+        let mut config = Config { path, values: BTreeMap::new(), lines: Vec::new() };
+
+        let Some(path) = &config.path else {
+            return Ok(config);
+        };
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(config),
+            Err(err) => return Err(err.into()),
+        };
+
+        for (index, line) in content.lines().enumerate() {
+            config.lines.push(line.to_string());
+
+            let invalid = |message: String| {
+                TasksError::InvalidArgument(format!("Invalid setting in {}, line {}: {message}", path.display(), index + 1))
+            };
+
+            let Some((key, value)) = parse_line(line).map_err(invalid)? else {
+                continue;
+            };
+            validate(key, value).map_err(|err| invalid(err.to_string()))?;
+
+            config.values.insert(key.to_string(), value.to_string());
+        }
+
+        Ok(config)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// All settings, sorted by key.
+    pub fn values(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Sets `key` to `value`, or removes it when `value` is `None`, and
+    /// saves the file. The setting's line is changed in place, or added at
+    /// the end; comments and other lines stay as they are.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<(), TasksError> {
+        let Some(path) = &self.path else {
+            return Err(TasksError::Unsupported("This store keeps no configuration".to_string()));
+        };
+
+        if let Some(value) = value {
+            validate(key, value)?;
+        }
+
+        let mut line = value.map(|value| format!("{key} = {value}"));
+        let mut lines = Vec::with_capacity(self.lines.len() + 1);
+        for existing in &self.lines {
+            match parse_line(existing) {
+                // The first line for the key takes the new value, and any
+                // repeats of it go.
+                Ok(Some((existing_key, _))) if existing_key == key => lines.extend(line.take()),
+                _ => lines.push(existing.clone()),
+            }
+        }
+        lines.extend(line);
+
+        let content: String = lines.iter().map(|line| format!("{line}\n")).collect();
+        write_atomically(path, &content)?;
+
+        self.lines = lines;
+        match value {
+            Some(value) => self.values.insert(key.to_string(), value.to_string()),
+            None => self.values.remove(key),
+        };
+
+        Ok(())
+    }
+}
+
+/// Splits a line into its key and value, or returns `None` for blank lines
+/// and comments.
+fn parse_line(line: &str) -> Result<Option<(&str, &str)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(COMMENT_PREFIX) {
+        return Ok(None);
+    }
+
+    let (key, value) = line.split_once('=').ok_or_else(|| format!("expected key = value, found {line:?}"))?;
+
+    Ok(Some((key.trim(), value.trim())))
+}
+
+/// Checks that `key` is known and `value` suits it.
+fn validate(key: &str, value: &str) -> Result<(), TasksError> {
+    match key {
+        ARCHIVE_AFTER => dates::ago(value, &SystemClock).map(|_| ()),
+        LIST_DEFAULT => parse_list(value).map(|_| ()),
+        LIST_COLUMNS => Column::parse_list(value).map(|_| ()),
+        COLOR_COMPLETED | COLOR_OVERDUE | COLOR_TODAY | COLOR_PRIORITY_HIGH | COLOR_PRIORITY_MEDIUM | COLOR_PRIORITY_LOW
+        | COLOR_TAG => value.parse::<Style>().map(|_| ()),
+        _ => {
+            let known: Vec<&str> = KEYS.iter().map(|(key, _)| *key).collect();
+            Err(TasksError::InvalidArgument(format!("Unknown setting {key:?}, expected one of {}", known.join(", "))))
+        },
+    }
+}
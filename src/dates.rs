@@ -0,0 +1,230 @@
+//! Parsing of the dates users type on the command line.
+//!
+//! Besides absolute `YYYY-MM-DD` dates, relative forms such as `tomorrow`,
+//! `fri`, `next monday`, `in 3 days`, `2w` or `eom` are resolved against a
+//! `Clock`, which tests can replace with a fixed one. A bare weekday means
+//! its next occurrence after today; `next <weekday>` means that day in the
+//! following Monday-based week.
+
+use chrono::{Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::{Due, TasksError};
+
+const TIME_FORMAT: &str = "%H:%M";
+
+const ACCEPTED_FORMS: &str = "YYYY-MM-DD, YYYY-MM-DDTHH:MM, today, tomorrow, yesterday, \
+    a weekday (mon, friday), next <weekday>, in <N> days|weeks|months|years, \
+    <N>d, <N>w, <N>m, <N>y, eow, eom, eoy; relative forms may end with a HH:MM time";
+
+/// The source of the current local time.
+pub trait Clock {
+    fn now(&self) -> NaiveDateTime;
+
+    fn today(&self) -> NaiveDate {
+        self.now().date()
+    }
+}
+
+/// The system clock in the local time zone.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// A clock stopped at a given time.
+pub struct FixedClock(pub NaiveDateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> NaiveDateTime {
+        self.0
+    }
+}
+
+/// Parses an absolute or relative date, with an optional trailing time.
+pub fn parse(input: &str, clock: &dyn Clock) -> Result<Due, TasksError> {
+    let invalid = || TasksError::InvalidArgument(format!("Invalid date {input:?}, accepted forms: {ACCEPTED_FORMS}"));
+
+    if let Ok(due) = input.trim().parse::<Due>() {
+        return Ok(due);
+    }
+
+    let normalized = input.trim().to_lowercase();
+
+    let (expression, time) = match normalized.rsplit_once(' ') {
+        Some((expression, time)) => match NaiveTime::parse_from_str(time, TIME_FORMAT) {
+            Ok(time) => (expression.trim_end(), Some(time)),
+            Err(_) => (normalized.as_str(), None),
+        },
+        None => (normalized.as_str(), None),
+    };
+
+    let date = parse_relative(expression, clock.today()).ok_or_else(invalid)?;
+
+    Ok(Due::new(date, time))
+}
+
+/// Parses an age such as `30d`, `2w` or `6 months` and returns the date
+/// that long before today.
+pub fn ago(input: &str, clock: &dyn Clock) -> Result<NaiveDate, TasksError> {
+    let invalid = || TasksError::InvalidArgument(format!("Invalid age {input:?}, expected e.g. 30d, 2w, 6m or 1y"));
+
+    let normalized = input.trim().to_lowercase();
+    let split = normalized.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (count, unit) = normalized.split_at(split);
+    let count: u32 = count.parse().map_err(|_| invalid())?;
+    let today = clock.today();
+
+    let date = match unit.trim() {
+        "d" | "day" | "days" => today.checked_sub_days(Days::new(u64::from(count))),
+        "w" | "week" | "weeks" => today.checked_sub_days(Days::new(7 * u64::from(count))),
+        "m" | "month" | "months" => today.checked_sub_months(Months::new(count)),
+        "y" | "year" | "years" => today.checked_sub_months(Months::new(12 * count)),
+        _ => None,
+    };
+
+    date.ok_or_else(invalid)
+}
+
+fn parse_relative(expression: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let words: Vec<&str> = expression.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["yesterday"] => today.pred_opt(),
+        ["eow"] => Some(end_of_week(today)),
+        ["eom"] => end_of_month(today),
+        ["eoy"] => NaiveDate::from_ymd_opt(today.year(), 12, 31),
+        ["next", weekday] => {
+            let start_of_next_week = today + Days::new(7 - u64::from(today.weekday().num_days_from_monday()));
+            let weekday = parse_weekday(weekday)?;
+            Some(start_of_next_week + Days::new(u64::from(weekday.num_days_from_monday())))
+        },
+        ["in", count, unit] => offset(today, count.parse().ok()?, unit),
+        [word] => match parse_weekday(word) {
+            Some(weekday) => Some(next_weekday(today, weekday)),
+            None => {
+                let split = word.find(|c: char| !c.is_ascii_digit())?;
+                let (count, unit) = word.split_at(split);
+                offset(today, count.parse().ok()?, unit)
+            },
+        },
+        _ => None,
+    }
+}
+
+/// The first `weekday` after `today`.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+
+    today + Days::new(u64::from(ahead))
+}
+
+fn end_of_week(today: NaiveDate) -> NaiveDate {
+    today + Days::new(u64::from(6 - today.weekday().num_days_from_monday()))
+}
+
+fn end_of_month(today: NaiveDate) -> Option<NaiveDate> {
+    let first = today.with_day(1)?;
+    (first + Months::new(1)).pred_opt()
+}
+
+fn offset(today: NaiveDate, count: u32, unit: &str) -> Option<NaiveDate> {
+    match unit {
+        "d" | "day" | "days" => today.checked_add_days(Days::new(u64::from(count))),
+        "w" | "week" | "weeks" => today.checked_add_days(Days::new(7 * u64::from(count))),
+        "m" | "month" | "months" => today.checked_add_months(Months::new(count)),
+        "y" | "year" | "years" => today.checked_add_months(Months::new(12 * count)),
+        _ => None,
+    }
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let weekday = match word {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wed" | "wednesday" => Weekday::Wed,
+        "thu" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        "sat" | "saturday" => Weekday::Sat,
+        "sun" | "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+
+    Some(weekday)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday, 14 October 2026, 10:00.
+    fn clock() -> FixedClock {
+        FixedClock(NaiveDate::from_ymd_opt(2026, 10, 14).unwrap().and_hms_opt(10, 0, 0).unwrap())
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn parse_date(input: &str) -> Due {
+        parse(input, &clock()).unwrap()
+    }
+
+    #[test]
+    fn bare_weekday_is_its_next_occurrence() {
+        assert_eq!(parse_date("fri"), Due::new(date(2026, 10, 16), None));
+        assert_eq!(parse_date("Wednesday"), Due::new(date(2026, 10, 21), None));
+    }
+
+    #[test]
+    fn next_weekday_is_in_the_following_week() {
+        assert_eq!(parse_date("next monday"), Due::new(date(2026, 10, 19), None));
+        assert_eq!(parse_date("next fri"), Due::new(date(2026, 10, 23), None));
+    }
+
+    #[test]
+    fn eom_is_last_day_of_month() {
+        assert_eq!(parse_date("eom"), Due::new(date(2026, 10, 31), None));
+        let february = FixedClock(date(2028, 2, 10).and_hms_opt(0, 0, 0).unwrap());
+        assert_eq!(parse("eom", &february).unwrap(), Due::new(date(2028, 2, 29), None));
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse_date("2w"), Due::new(date(2026, 10, 28), None));
+        assert_eq!(parse_date("in 3 days"), Due::new(date(2026, 10, 17), None));
+        assert_eq!(parse_date("1m"), Due::new(date(2026, 11, 14), None));
+    }
+
+    #[test]
+    fn trailing_time() {
+        let time = NaiveTime::from_hms_opt(9, 30, 0);
+        assert_eq!(parse_date("tomorrow 09:30"), Due::new(date(2026, 10, 15), time));
+        assert_eq!(parse_date("next monday 09:30"), Due::new(date(2026, 10, 19), time));
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(parse_date("2026-12-01"), Due::new(date(2026, 12, 1), None));
+    }
+
+    #[test]
+    fn invalid_date_lists_accepted_forms() {
+        let err = parse("someday", &clock()).unwrap_err();
+        assert_eq!(err.to_string(), format!("Invalid date \"someday\", accepted forms: {ACCEPTED_FORMS}"));
+        assert!(parse("next", &clock()).is_err());
+        assert!(parse("3x", &clock()).is_err());
+    }
+
+    #[test]
+    fn ago_counts_back_from_today() {
+        assert_eq!(ago("30d", &clock()).unwrap(), date(2026, 9, 14));
+        assert_eq!(ago("1y", &clock()).unwrap(), date(2025, 10, 14));
+        assert!(ago("soon", &clock()).is_err());
+    }
+}
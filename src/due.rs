@@ -0,0 +1,115 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::TasksError;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M";
+
+/// When a task is due: a date, optionally with a time of day.
+///
+/// A due date without a time lasts until the end of that day, so it orders
+/// after any time on the same date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Due {
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+}
+
+/// How a pending task's due date relates to the current time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DueStatus {
+    Overdue,
+    Today,
+    Upcoming,
+}
+
+impl Due {
+    pub fn new(date: NaiveDate, time: Option<NaiveTime>) -> Self {
+        Self { date, time }
+    }
+
+    /// The last moment at which the task is not yet overdue.
+    pub fn deadline(&self) -> NaiveDateTime {
+        let time = self.time.unwrap_or(NaiveTime::from_hms_opt(23, 59, 59).unwrap());
+        self.date.and_time(time)
+    }
+
+    pub fn status(&self, now: NaiveDateTime) -> DueStatus {
+        if now > self.deadline() {
+            DueStatus::Overdue
+        } else if now.date() == self.date {
+            DueStatus::Today
+        } else {
+            DueStatus::Upcoming
+        }
+    }
+
+    /// Whether this is due before `bound`. A bound without a time compares
+    /// dates only, so "before the 20th" excludes anything due on the 20th.
+    pub fn is_before(&self, bound: &Due) -> bool {
+        match bound.time {
+            Some(_) => self.deadline() < bound.deadline(),
+            None => self.date < bound.date,
+        }
+    }
+
+    /// Whether this is due after `bound`, comparing dates only when the bound
+    /// has no time.
+    pub fn is_after(&self, bound: &Due) -> bool {
+        match bound.time {
+            Some(_) => self.deadline() > bound.deadline(),
+            None => self.date > bound.date,
+        }
+    }
+}
+
+impl Ord for Due {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deadline().cmp(&other.deadline())
+    }
+}
+
+impl PartialOrd for Due {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Parses `YYYY-MM-DD`, optionally followed by `THH:MM` or ` HH:MM`.
+impl FromStr for Due {
+    type Err = TasksError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || TasksError::InvalidArgument(format!(
+            "Invalid date {value:?}, expected YYYY-MM-DD or YYYY-MM-DDTHH:MM",
+        ));
+
+        let (date, time) = match value.split_once(['T', ' ']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (value, None),
+        };
+
+        let date = NaiveDate::parse_from_str(date, DATE_FORMAT).map_err(|_| invalid())?;
+        let time = time
+            .map(|time| NaiveTime::parse_from_str(time, TIME_FORMAT))
+            .transpose()
+            .map_err(|_| invalid())?;
+
+        Ok(Self::new(date, time))
+    }
+}
+
+impl fmt::Display for Due {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.date.format(DATE_FORMAT))?;
+        if let Some(time) = self.time {
+            write!(f, "T{}", time.format(TIME_FORMAT))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{BuildHasher, RandomState};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+
+use crate::TasksError;
+
+const DEFAULT_EDITOR: &str = "vi";
+/// How many names to try for the temporary file before giving up.
+const TEMP_FILE_ATTEMPTS: u32 = 16;
+
+/// Lets the user edit `text` in `$VISUAL` or `$EDITOR`, falling back to
+/// `vi`, and returns the result with its lines joined by spaces.
+pub fn edit(text: &str) -> Result<String, TasksError> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
+
+    // The editor variable may carry arguments, as in `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or(DEFAULT_EDITOR);
+
+    let (path, mut file) = create_temp_file()?;
+    let written = writeln!(file, "{text}");
+    drop(file);
+    if let Err(err) = written {
+        fs::remove_file(&path)?;
+        return Err(err.into());
+    }
+
+    let status = process::Command::new(program).args(words).arg(&path).status();
+    let edited = fs::read_to_string(&path);
+    fs::remove_file(&path)?;
+
+    let status = status.map_err(|err| TasksError::InvalidArgument(format!("Could not run editor {editor:?}: {err}")))?;
+    if !status.success() {
+        return Err(TasksError::InvalidArgument(format!("Editor {editor:?} failed with {status}, nothing changed")));
+    }
+
+    Ok(edited?.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join(" "))
+}
+
+/// Creates a new file with an unpredictable name in the temporary
+/// directory, readable only by the user. The file must not exist yet, so a
+/// file or symlink planted there by someone else is never written through.
+fn create_temp_file() -> io::Result<(PathBuf, File)> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    for _ in 0..TEMP_FILE_ATTEMPTS {
+        // `RandomState` keys are seeded randomly, so names cannot be guessed.
+        let random = RandomState::new().hash_one(process::id());
+        let path = env::temp_dir().join(format!("todos-edit-{random:016x}.txt"));
+
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "could not create a temporary file to edit"))
+}
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::TaskRef;
+
+/// Everything that can go wrong while building or running a command.
+#[derive(Debug)]
+pub enum TasksError {
+    /// A database line could not be parsed.
+    Parse { line: usize, message: String },
+    /// The command name is not known.
+    UnknownCommand(String),
+    /// A global flag is not known.
+    UnknownFlag(String),
+    /// A required argument was not given.
+    MissingArgument(&'static str),
+    /// An argument was given but cannot be used.
+    InvalidArgument(String),
+    /// A filter expression is malformed; `position` counts characters.
+    InvalidFilter {
+        expression: String,
+        position: usize,
+        message: String,
+    },
+    /// The command is not available for this store or build.
+    Unsupported(String),
+    /// No task matches the given id or position.
+    TaskNotFound(TaskRef),
+    /// Reading or writing the database failed.
+    Io(io::Error),
+    /// A storage backend other than the file system failed.
+    Storage(Box<dyn Error + Send + Sync>),
+    /// Another process held the database lock for longer than `timeout`.
+    Locked {
+        path: PathBuf,
+        holder: Option<u32>,
+        timeout: Duration,
+    },
+}
+
+impl TasksError {
+    /// A stable name for the kind of error, as used in JSON output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TasksError::Parse { .. } => "parse",
+            TasksError::UnknownCommand(_) => "unknown_command",
+            TasksError::UnknownFlag(_) => "unknown_flag",
+            TasksError::MissingArgument(_) => "missing_argument",
+            TasksError::InvalidArgument(_) => "invalid_argument",
+            TasksError::InvalidFilter { .. } => "invalid_filter",
+            TasksError::Unsupported(_) => "unsupported",
+            TasksError::TaskNotFound(_) => "task_not_found",
+            TasksError::Io(_) => "io",
+            TasksError::Storage(_) => "storage",
+            TasksError::Locked { .. } => "locked",
+        }
+    }
+}
+
+impl fmt::Display for TasksError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TasksError::Parse { line, message } => write!(f, "Corrupt database, line {line}: {message}"),
+            TasksError::UnknownCommand(command) => write!(f, "Unsupported command {command:?}"),
+            TasksError::UnknownFlag(flag) => write!(f, "Unsupported flag {flag}"),
+            TasksError::MissingArgument(argument) => write!(f, "Missing {argument}"),
+            TasksError::InvalidArgument(message) => write!(f, "{message}"),
+            TasksError::InvalidFilter { expression, position, message } => {
+                writeln!(f, "Invalid filter at column {}: {message}", position + 1)?;
+                writeln!(f, "  {expression}")?;
+                write!(f, "  {}^", " ".repeat(*position))
+            },
+            TasksError::Unsupported(message) => write!(f, "{message}"),
+            TasksError::TaskNotFound(task_ref) => write!(f, "Missing task {task_ref}"),
+            TasksError::Io(err) => write!(f, "{err}"),
+            TasksError::Storage(err) => write!(f, "Storage error: {err}"),
+            TasksError::Locked { path, holder, timeout } => {
+                write!(f, "Timed out after {}s waiting for {}: held by ", timeout.as_secs_f32(), path.display())?;
+                match holder {
+                    Some(pid) => write!(f, "process {pid}"),
+                    None => write!(f, "another process"),
+                }
+            },
+        }
+    }
+}
+
+impl Error for TasksError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TasksError::Io(err) => Some(err),
+            TasksError::Storage(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TasksError {
+    fn from(err: io::Error) -> Self {
+        TasksError::Io(err)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for TasksError {
+    fn from(err: rusqlite::Error) -> Self {
+        TasksError::Storage(Box::new(err))
+    }
+}
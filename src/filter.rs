@@ -0,0 +1,494 @@
+//! The filter language used to select tasks, for example
+//! `status:pending and (+bug or priority:H) and due.before:fri`.
+//!
+//! Terms are `+tag`, `-tag`, `field:value` conditions and bare words, which
+//! match task ids or words in the name. Terms combine with `and`, `or`, `not`
+//! and parentheses; adjacent terms are joined with an implicit `and`, and
+//! `and` binds tighter than `or`. Values containing spaces can be quoted, as
+//! in `due.before:"next monday"`.
+
+use crate::task::{is_valid_tag, TAG_PREFIX};
+use crate::{dates, Clock, Due, Priority, Task, TaskRef, TasksError, POSITION_PREFIX};
+
+const FIELDS: &str = "status, priority, project, list, tag, due, due.before, due.after, name, id, parent";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    Condition(Condition),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    Completed(bool),
+    Priority(Option<Priority>),
+    /// An empty project selects tasks without one.
+    Project(String),
+    List(String),
+    Tag(String),
+    Due(Option<Due>),
+    DueBefore(Due),
+    DueAfter(Due),
+    /// Case-insensitive substring of the name.
+    Name(String),
+    Id(u32),
+    /// `None` selects top-level tasks.
+    Parent(Option<u32>),
+}
+
+impl Filter {
+    /// Parses `expression`, resolving relative dates against `clock`.
+    pub fn parse(expression: &str, clock: &dyn Clock) -> Result<Filter, TasksError> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser { expression, tokens, next: 0, clock };
+
+        let filter = parser.or()?;
+
+        match parser.peek() {
+            None => Ok(filter),
+            Some(Token { kind: TokenKind::Close, position }) => Err(parser.error(*position, "unmatched ')'")),
+            Some(token) => Err(parser.error(token.position, "expected 'and', 'or' or the end of the filter")),
+        }
+    }
+
+    /// Narrows the filter to tasks in the named list.
+    pub fn within(self, list: &str) -> Filter {
+        Filter::And(Box::new(self), Box::new(Filter::Condition(Condition::List(list.to_string()))))
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        match self {
+            Filter::And(left, right) => left.matches(task) && right.matches(task),
+            Filter::Or(left, right) => left.matches(task) || right.matches(task),
+            Filter::Not(filter) => !filter.matches(task),
+            Filter::Condition(condition) => condition.matches(task),
+        }
+    }
+}
+
+impl Condition {
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Condition::Completed(completed) => task.is_completed() == *completed,
+            Condition::Priority(priority) => task.priority() == *priority,
+            Condition::Project(project) => task.project().unwrap_or_default() == project,
+            Condition::List(list) => task.list() == list,
+            Condition::Tag(tag) => task.has_tag(tag),
+            Condition::Due(None) => task.due().is_none(),
+            Condition::Due(Some(due)) => task.due().is_some_and(|task_due| task_due.date == due.date),
+            Condition::DueBefore(bound) => task.due().is_some_and(|due| due.is_before(bound)),
+            Condition::DueAfter(bound) => task.due().is_some_and(|due| due.is_after(bound)),
+            Condition::Name(text) => task.name().to_lowercase().contains(text),
+            Condition::Id(id) => task.id() == *id,
+            Condition::Parent(parent) => task.parent() == *parent,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum TokenKind {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    /// Character offset into the expression.
+    position: usize,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, TasksError> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().enumerate().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            },
+            '(' | ')' => {
+                chars.next();
+                let kind = if c == '(' { TokenKind::Open } else { TokenKind::Close };
+                tokens.push(Token { kind, position });
+            },
+            _ => {
+                let mut word = String::new();
+                let mut quote_start = None;
+
+                while let Some(&(index, c)) = chars.peek() {
+                    match c {
+                        '"' => quote_start = if quote_start.is_some() { None } else { Some(index) },
+                        '(' | ')' if quote_start.is_none() => break,
+                        _ if c.is_whitespace() && quote_start.is_none() => break,
+                        _ => word.push(c),
+                    }
+                    chars.next();
+                }
+
+                if let Some(start) = quote_start {
+                    return Err(invalid_filter(expression, start, "unterminated quote"));
+                }
+
+                let kind = match word.to_lowercase().as_str() {
+                    "and" => TokenKind::And,
+                    "or" => TokenKind::Or,
+                    "not" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                };
+                tokens.push(Token { kind, position });
+            },
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    expression: &'a str,
+    tokens: Vec<Token>,
+    next: usize,
+    clock: &'a dyn Clock,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn end_position(&self) -> usize {
+        self.expression.chars().count()
+    }
+
+    fn error(&self, position: usize, message: impl Into<String>) -> TasksError {
+        invalid_filter(self.expression, position, message)
+    }
+
+    fn or(&mut self) -> Result<Filter, TasksError> {
+        let mut filter = self.and()?;
+
+        while self.peek().is_some_and(|token| token.kind == TokenKind::Or) {
+            self.next += 1;
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, TasksError> {
+        let mut filter = self.unary()?;
+
+        loop {
+            match self.peek().map(|token| &token.kind) {
+                Some(TokenKind::And) => self.next += 1,
+                Some(TokenKind::Open | TokenKind::Not | TokenKind::Word(_)) => {},
+                _ => return Ok(filter),
+            }
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Filter, TasksError> {
+        let Some(token) = self.tokens.get(self.next) else {
+            return Err(self.error(self.end_position(), "expected a condition"));
+        };
+        let position = token.position;
+        self.next += 1;
+
+        match &token.kind {
+            TokenKind::Not => Ok(Filter::Not(Box::new(self.unary()?))),
+            TokenKind::Open => {
+                let filter = self.or()?;
+                match self.peek() {
+                    Some(Token { kind: TokenKind::Close, .. }) => {
+                        self.next += 1;
+                        Ok(filter)
+                    },
+                    Some(token) => Err(self.error(token.position, "expected ')'")),
+                    None => Err(self.error(self.end_position(), format!("expected ')' to close the '(' at column {}", position + 1))),
+                }
+            },
+            TokenKind::Word(word) => {
+                let word = word.clone();
+                self.term(&word, position)
+            },
+            TokenKind::Close | TokenKind::And | TokenKind::Or => Err(self.error(position, "expected a condition")),
+        }
+    }
+
+    fn term(&self, word: &str, position: usize) -> Result<Filter, TasksError> {
+        if let Some(tag) = word.strip_prefix(TAG_PREFIX) {
+            return Ok(Filter::Condition(Condition::Tag(self.tag(tag, position)?)));
+        }
+
+        if let Some(tag) = word.strip_prefix('-') {
+            let condition = Condition::Tag(self.tag(tag, position)?);
+            return Ok(Filter::Not(Box::new(Filter::Condition(condition))));
+        }
+
+        let Some((field, value)) = word.split_once(':') else {
+            let condition = match word.parse::<TaskRef>() {
+                Ok(TaskRef::Id(id)) => Condition::Id(id),
+                _ => Condition::Name(word.to_lowercase()),
+            };
+            return Ok(Filter::Condition(condition));
+        };
+
+        // Errors about the value point just past the colon.
+        let value_position = position + field.chars().count() + 1;
+        let invalid_value = |err: TasksError| self.error(value_position, err.to_string());
+
+        let condition = match field.to_lowercase().as_str() {
+            "status" => match value.to_lowercase().as_str() {
+                "pending" | "open" => Condition::Completed(false),
+                "completed" | "done" => Condition::Completed(true),
+                _ => return Err(self.error(value_position, "expected pending or completed")),
+            },
+            "priority" => match value {
+                "" | "none" => Condition::Priority(None),
+                _ => Condition::Priority(Some(value.parse().map_err(invalid_value)?)),
+            },
+            "project" => Condition::Project(value.to_string()),
+            "list" => Condition::List(value.to_string()),
+            "tag" => Condition::Tag(self.tag(value, value_position)?),
+            "due" => match value {
+                "" | "none" => Condition::Due(None),
+                _ => Condition::Due(Some(dates::parse(value, self.clock).map_err(invalid_value)?)),
+            },
+            "due.before" => Condition::DueBefore(dates::parse(value, self.clock).map_err(invalid_value)?),
+            "due.after" => Condition::DueAfter(dates::parse(value, self.clock).map_err(invalid_value)?),
+            "name" => Condition::Name(value.to_lowercase()),
+            "id" => match value.parse::<TaskRef>() {
+                Ok(TaskRef::Id(id)) => Condition::Id(id),
+                _ => return Err(self.error(value_position, "expected a task id")),
+            },
+            "parent" => match value.parse::<TaskRef>() {
+                _ if matches!(value, "" | "none") => Condition::Parent(None),
+                Ok(TaskRef::Id(id)) => Condition::Parent(Some(id)),
+                _ => return Err(self.error(value_position, "expected a task id or none")),
+            },
+            _ => return Err(self.error(position, format!("unknown field {field:?}, expected one of {FIELDS}"))),
+        };
+
+        Ok(Filter::Condition(condition))
+    }
+
+    fn tag(&self, tag: &str, position: usize) -> Result<String, TasksError> {
+        if is_valid_tag(tag) {
+            Ok(tag.to_string())
+        } else {
+            Err(self.error(position, format!("invalid tag {tag:?}")))
+        }
+    }
+}
+
+fn invalid_filter(expression: &str, position: usize, message: impl Into<String>) -> TasksError {
+    TasksError::InvalidFilter {
+        expression: expression.to_string(),
+        position,
+        message: message.into(),
+    }
+}
+
+/// The tasks a command acts on: a list of ids, positions and id ranges
+/// such as `1,4,7-12`, or all tasks matching a filter.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Selector {
+    Tasks(Vec<TaskSpan>),
+    Filter(Filter),
+}
+
+/// One entry of a task list selector.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskSpan {
+    /// A task that must exist.
+    Task(TaskRef),
+    /// An inclusive range of ids; ids without a task are skipped.
+    Ids(u32, u32),
+}
+
+impl Selector {
+    /// Reads the words after a command name. Words made only of ids,
+    /// positions and ranges select those tasks; anything else is read as a
+    /// filter. Words that start like an id, position or range but are not
+    /// one, such as `4x` or `-5`, are rejected rather than read as a filter,
+    /// so a typo cannot select other tasks.
+    pub fn parse(words: &[&str], clock: &dyn Clock) -> Result<Self, TasksError> {
+        if words.is_empty() {
+            return Err(TasksError::MissingArgument("task id or filter"));
+        }
+
+        let spans: Option<Vec<TaskSpan>> = words
+            .iter()
+            .flat_map(|word| word.split(','))
+            .map(parse_span)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .collect();
+
+        match spans {
+            Some(spans) => Ok(Selector::Tasks(spans)),
+            None => Ok(Selector::Filter(Filter::parse(&words.join(" "), clock)?)),
+        }
+    }
+
+    /// Narrows a filter to tasks in the named list. Tasks picked by id are
+    /// found in any list.
+    pub fn within(self, list: &str) -> Selector {
+        match self {
+            Selector::Filter(filter) => Selector::Filter(filter.within(list)),
+            tasks => tasks,
+        }
+    }
+}
+
+/// Parses `4`, `%0` or `7-12`, returning `None` for words that cannot be
+/// mistaken for one.
+fn parse_span(value: &str) -> Result<Option<TaskSpan>, TasksError> {
+    if let Ok(task_ref) = value.parse() {
+        return Ok(Some(TaskSpan::Task(task_ref)));
+    }
+
+    if let Some((start, end)) = value.split_once('-') {
+        match (start.parse(), end.parse()) {
+            (Ok(TaskRef::Id(start)), Ok(TaskRef::Id(end))) if start <= end => return Ok(Some(TaskSpan::Ids(start, end))),
+            (Ok(TaskRef::Id(_)), Ok(TaskRef::Id(_))) => {
+                return Err(TasksError::InvalidArgument(format!(
+                    "Invalid range {value:?}, the first id must not be larger"
+                )));
+            },
+            _ => {},
+        }
+    }
+
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    if unsigned.starts_with(|c: char| c.is_ascii_digit() || c == POSITION_PREFIX) {
+        return Err(TasksError::InvalidArgument(format!(
+            "Invalid task id or range {value:?}, expected e.g. 4, {POSITION_PREFIX}0 or 7-12",
+        )));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::FixedClock;
+
+    fn parse(expression: &str) -> Result<Filter, TasksError> {
+        let clock = FixedClock(NaiveDate::from_ymd_opt(2026, 10, 14).unwrap().and_hms_opt(10, 0, 0).unwrap());
+        Filter::parse(expression, &clock)
+    }
+
+    fn tag(tag: &str) -> Filter {
+        Filter::Condition(Condition::Tag(tag.to_string()))
+    }
+
+    fn and(left: Filter, right: Filter) -> Filter {
+        Filter::And(Box::new(left), Box::new(right))
+    }
+
+    fn or(left: Filter, right: Filter) -> Filter {
+        Filter::Or(Box::new(left), Box::new(right))
+    }
+
+    fn not(filter: Filter) -> Filter {
+        Filter::Not(Box::new(filter))
+    }
+
+    /// The position and message of a filter error.
+    fn error(expression: &str) -> (usize, String) {
+        match parse(expression) {
+            Err(TasksError::InvalidFilter { position, message, .. }) => (position, message),
+            other => panic!("expected an invalid filter, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(parse("+a or +b and +c").unwrap(), or(tag("a"), and(tag("b"), tag("c"))));
+        assert_eq!(parse("+a and +b or +c").unwrap(), or(and(tag("a"), tag("b")), tag("c")));
+    }
+
+    #[test]
+    fn adjacent_terms_are_anded() {
+        assert_eq!(parse("+a +b or +c").unwrap(), or(and(tag("a"), tag("b")), tag("c")));
+    }
+
+    #[test]
+    fn not_applies_to_the_next_term() {
+        assert_eq!(parse("not +a or +b").unwrap(), or(not(tag("a")), tag("b")));
+        assert_eq!(parse("not (+a or +b)").unwrap(), not(or(tag("a"), tag("b"))));
+        assert_eq!(parse("-a").unwrap(), not(tag("a")));
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(parse("(+a or +b) and +c").unwrap(), and(or(tag("a"), tag("b")), tag("c")));
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(parse("status:done").unwrap(), Filter::Condition(Condition::Completed(true)));
+        assert_eq!(parse("priority:none").unwrap(), Filter::Condition(Condition::Priority(None)));
+        assert_eq!(parse("parent:4").unwrap(), Filter::Condition(Condition::Parent(Some(4))));
+        assert_eq!(
+            parse("due.before:\"next monday\"").unwrap(),
+            Filter::Condition(Condition::DueBefore(Due::new(NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(), None))),
+        );
+        assert_eq!(parse("7").unwrap(), Filter::Condition(Condition::Id(7)));
+        assert_eq!(parse("Milk").unwrap(), Filter::Condition(Condition::Name("milk".to_string())));
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(error("+a and"), (6, "expected a condition".to_string()));
+        assert_eq!(error("+a or or +b"), (6, "expected a condition".to_string()));
+        assert_eq!(error("(+a or +b"), (9, "expected ')' to close the '(' at column 1".to_string()));
+        assert_eq!(error("+a )"), (3, "unmatched ')'".to_string()));
+        assert_eq!(error("status:maybe"), (7, "expected pending or completed".to_string()));
+        assert_eq!(error("+a colour:red").0, 3);
+        assert_eq!(error("name:\"open").0, 5);
+    }
+
+    fn select(words: &[&str]) -> Result<Selector, TasksError> {
+        let clock = FixedClock(NaiveDate::from_ymd_opt(2026, 10, 14).unwrap().and_hms_opt(10, 0, 0).unwrap());
+        Selector::parse(words, &clock)
+    }
+
+    #[test]
+    fn selector_reads_ids_positions_and_ranges() {
+        let spans = vec![
+            TaskSpan::Task(TaskRef::Id(1)),
+            TaskSpan::Task(TaskRef::Id(4)),
+            TaskSpan::Ids(7, 12),
+            TaskSpan::Task(TaskRef::Position(0)),
+        ];
+        assert_eq!(select(&["1,4", "7-12", "%0"]).unwrap(), Selector::Tasks(spans));
+        assert_eq!(select(&["+bug", "or", "priority:H"]).unwrap(), Selector::Filter(or(tag("bug"), parse("priority:H").unwrap())));
+    }
+
+    #[test]
+    fn selector_rejects_mistyped_ids() {
+        for word in ["-5", "4x", "0", "%x", "4-", "3-1"] {
+            assert!(matches!(select(&[word]), Err(TasksError::InvalidArgument(_))), "{word} was accepted");
+        }
+        assert!(select(&["+bug", "2fa"]).is_err());
+        assert!(select(&["name:2fa"]).is_ok());
+    }
+
+    #[test]
+    fn error_display_points_at_column() {
+        let err = parse("+a and").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid filter at column 7: expected a condition\n  +a and\n        ^");
+    }
+}
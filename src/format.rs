@@ -0,0 +1,161 @@
+//! On-disk line format of the text database.
+//!
+//! Version 1 files have no header and store `completed|name` per line, with
+//! no escaping. Version 2 files start with a `# tasks v2` header; fields are
+//! separated by `|`, and backslashes, separators and line breaks inside a
+//! field are escaped with a backslash. Version 3 follows the name with
+//! `key:value` attribute fields, such as the task's `id:4`. Timestamps are
+//! written in UTC as RFC 3339, e.g. `created:2026-10-18T09:30:00Z`. Its
+//! header may also record the next id to give out, as in
+//! `# tasks v3 next:12`, so ids of deleted tasks are not reused.
+
+use std::fmt;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::SEPARATOR;
+
+pub const VERSION: u32 = 3;
+const HEADER_PREFIX: &str = "# tasks v";
+const ESCAPE: char = '\\';
+const ATTRIBUTE_SEPARATOR: char = ':';
+const NEXT_ID_KEY: &str = "next";
+
+/// What a header line declares.
+#[derive(Debug, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    /// The lowest id never given to a task, or 0 if not recorded.
+    pub next_id: u32,
+}
+
+pub fn header(next_id: u32) -> String {
+    format!("{HEADER_PREFIX}{VERSION} {NEXT_ID_KEY}{ATTRIBUTE_SEPARATOR}{next_id}")
+}
+
+/// Parses a header line, returning `None` if the line is not a header
+/// (meaning a version 1 file).
+pub fn parse_header(line: &str) -> Result<Option<Header>, String> {
+    let Some(rest) = line.strip_prefix(HEADER_PREFIX) else {
+        return Ok(None);
+    };
+    let invalid = || format!("invalid format header {line:?}");
+
+    let mut words = rest.split_whitespace();
+    let version = match words.next().map(str::parse::<u32>) {
+        Some(Ok(version)) if (1..=VERSION).contains(&version) => version,
+        Some(Ok(version)) => return Err(format!("unsupported format version {version}, newest known is {VERSION}")),
+        _ => return Err(invalid()),
+    };
+
+    let mut next_id = 0;
+    for word in words {
+        next_id = match word.split_once(ATTRIBUTE_SEPARATOR) {
+            Some((NEXT_ID_KEY, value)) => value.parse().map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        };
+    }
+
+    Ok(Some(Header { version, next_id }))
+}
+
+/// Escapes a field so it can be joined with `SEPARATOR` on a single line.
+pub fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+
+    for c in field.chars() {
+        match c {
+            ESCAPE => escaped.push_str("\\\\"),
+            SEPARATOR => escaped.push_str("\\|"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Splits a version 2 line on unescaped separators and unescapes each field.
+pub fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ESCAPE => match chars.next() {
+                Some(ESCAPE) => field.push(ESCAPE),
+                Some(SEPARATOR) => field.push(SEPARATOR),
+                Some('n') => field.push('\n'),
+                Some('r') => field.push('\r'),
+                Some(other) => return Err(format!("invalid escape sequence \\{other}")),
+                None => return Err("line ends with a lone backslash".to_string()),
+            },
+            SEPARATOR => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    Ok(fields)
+}
+
+/// Formats an attribute field, to be escaped like any other field.
+pub fn attribute(key: &str, value: impl fmt::Display) -> String {
+    format!("{key}{ATTRIBUTE_SEPARATOR}{value}")
+}
+
+/// Splits an unescaped attribute field into its key and value.
+pub fn split_attribute(field: &str) -> Result<(&str, &str), String> {
+    field
+        .split_once(ATTRIBUTE_SEPARATOR)
+        .ok_or_else(|| format!("invalid attribute {field:?}, expected key{ATTRIBUTE_SEPARATOR}value"))
+}
+
+/// Formats a timestamp in UTC to the second.
+pub fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| format!("invalid timestamp {value:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_fields_round_trip() {
+        let fields = ["plain", "a|b", "back\\slash", "two\nlines\r", "", "\\|\\n"];
+        let line: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        let line = line.join(&SEPARATOR.to_string());
+
+        assert_eq!(split(&line).unwrap(), fields);
+    }
+
+    #[test]
+    fn escape_keeps_separators_off_the_line() {
+        assert_eq!(escape("a|b\nc\\"), "a\\|b\\nc\\\\");
+    }
+
+    #[test]
+    fn split_rejects_bad_escapes() {
+        assert_eq!(split("a\\x").unwrap_err(), "invalid escape sequence \\x");
+        assert_eq!(split("a\\").unwrap_err(), "line ends with a lone backslash");
+    }
+
+    #[test]
+    fn headers() {
+        assert_eq!(parse_header(&header(12)), Ok(Some(Header { version: VERSION, next_id: 12 })));
+        assert_eq!(parse_header("# tasks v2"), Ok(Some(Header { version: 2, next_id: 0 })));
+        assert_eq!(parse_header("0|Buy milk"), Ok(None));
+        assert!(parse_header("# tasks v99").is_err());
+        assert!(parse_header("# tasks vX").is_err());
+        assert!(parse_header("# tasks v3 next:x").is_err());
+        assert!(parse_header("# tasks v3 last:4").is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! The operation journal kept next to a database, which makes changes
+//! reversible.
+//!
+//! Every command that changes tasks appends an operation listing the
+//! affected tasks before (`-`) and after (`+`) the change, and `undo` and
+//! `redo` append markers naming the operation they reverted or replayed.
+//! The file is only ever appended to:
+//!
+//! ```text
+//! # tasks journal v1
+//! op|1|2026-10-18T09:30:00Z|delete
+//! -|0\|Fix login\|id:4
+//! undo|1|2026-10-18T09:31:00Z
+//! ```
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::{format, Task, TasksError, SEPARATOR};
+
+pub(crate) const JOURNAL_SUFFIX: &str = ".journal";
+const HEADER: &str = "# tasks journal v1";
+const BEFORE: &str = "-";
+const AFTER: &str = "+";
+/// How much of the journal is read at a time when looking for the last
+/// operation from the end.
+const TAIL_CHUNK: u64 = 8 * 1024;
+
+/// A recorded change to the task list.
+#[derive(Clone, Debug)]
+pub struct Operation {
+    pub number: u32,
+    pub time: DateTime<Utc>,
+    /// The name of the command that made the change.
+    pub command: String,
+    /// The affected tasks as they were before the change; added tasks are
+    /// missing here.
+    pub before: Vec<Task>,
+    /// The affected tasks after the change; deleted tasks are missing here.
+    pub after: Vec<Task>,
+}
+
+impl Operation {
+    /// Ids of the affected tasks, each once, in order.
+    pub fn task_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.before.iter().chain(&self.after).map(Task::id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Entry {
+    Operation(Operation),
+    Undo { number: u32, time: DateTime<Utc> },
+    Redo { number: u32, time: DateTime<Utc> },
+}
+
+/// The append-only journal file.
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads every entry, oldest first. A missing journal is empty.
+    pub fn entries(&self) -> Result<Vec<Entry>, TasksError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut entries = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let parse_error = |message: String| TasksError::Parse {
+                line: index + 1,
+                message: format!("journal {}: {message}", self.path.display()),
+            };
+
+            if index == 0 && line == HEADER {
+                continue;
+            }
+
+            let fields = format::split(line).map_err(parse_error)?;
+            let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+
+            match fields.as_slice() {
+                ["op", number, time, command] => entries.push(Entry::Operation(Operation {
+                    number: parse_number(number).map_err(parse_error)?,
+                    time: format::parse_timestamp(time).map_err(parse_error)?,
+                    command: command.to_string(),
+                    before: Vec::new(),
+                    after: Vec::new(),
+                })),
+                ["undo", number, time] => entries.push(Entry::Undo {
+                    number: parse_number(number).map_err(parse_error)?,
+                    time: format::parse_timestamp(time).map_err(parse_error)?,
+                }),
+                ["redo", number, time] => entries.push(Entry::Redo {
+                    number: parse_number(number).map_err(parse_error)?,
+                    time: format::parse_timestamp(time).map_err(parse_error)?,
+                }),
+                [side @ (BEFORE | AFTER), task] => {
+                    let Some(Entry::Operation(operation)) = entries.last_mut() else {
+                        return Err(parse_error("task line outside an operation".to_string()));
+                    };
+                    let task = Task::from_string(task).map_err(parse_error)?;
+                    if *side == BEFORE {
+                        operation.before.push(task);
+                    } else {
+                        operation.after.push(task);
+                    }
+                },
+                _ => return Err(parse_error(format!("invalid journal line {line:?}"))),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Splits the recorded operations into those in effect and those undone
+    /// and not yet redone, both oldest first. A new operation discards
+    /// everything undone before it.
+    pub fn stacks(&self) -> Result<(Vec<Operation>, Vec<Operation>), TasksError> {
+        let mut applied: Vec<Operation> = Vec::new();
+        let mut undone: Vec<Operation> = Vec::new();
+
+        for entry in self.entries()? {
+            match entry {
+                Entry::Operation(operation) => {
+                    applied.push(operation);
+                    undone.clear();
+                },
+                Entry::Undo { .. } => undone.extend(applied.pop()),
+                Entry::Redo { .. } => applied.extend(undone.pop()),
+            }
+        }
+
+        Ok((applied, undone))
+    }
+
+    /// Appends an operation, numbered after the last one.
+    pub(crate) fn record(&mut self, command: &str, before: &[Task], after: &[Task]) -> Result<u32, TasksError> {
+        let number = self.last_number()? + 1;
+
+        let mut lines = vec![join(&["op", &number.to_string(), &format::timestamp(Utc::now()), command])];
+        lines.extend(before.iter().map(|task| join(&[BEFORE, &task.to_string()])));
+        lines.extend(after.iter().map(|task| join(&[AFTER, &task.to_string()])));
+        self.append(&lines)?;
+
+        Ok(number)
+    }
+
+    /// The number of the newest operation, or 0 if there is none. Numbers
+    /// only grow, so this reads the journal backwards from its end up to the
+    /// last `op` line instead of parsing all of it.
+    fn last_number(&self) -> Result<u32, TasksError> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut end = file.metadata()?.len();
+        // The bytes after `end` not yet known to be whole lines.
+        let mut tail: Vec<u8> = Vec::new();
+
+        while end > 0 {
+            let start = end.saturating_sub(TAIL_CHUNK);
+            let mut chunk = vec![0; (end - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut chunk)?;
+            chunk.append(&mut tail);
+            tail = chunk;
+            end = start;
+
+            // The first line is cut off unless the start of the file was read.
+            let first_whole = match (end, tail.iter().position(|&byte| byte == b'\n')) {
+                (0, _) => 0,
+                (_, Some(newline)) => newline + 1,
+                (_, None) => continue,
+            };
+
+            for line in tail[first_whole..].split(|&byte| byte == b'\n').rev() {
+                if !line.starts_with(b"op|") {
+                    continue;
+                }
+                let fields = String::from_utf8(line.to_vec()).ok().and_then(|line| format::split(&line).ok());
+                match fields.as_deref().and_then(|fields| parse_number(fields.get(1)?).ok()) {
+                    Some(number) => return Ok(number),
+                    // A full read reports where the journal is corrupt.
+                    None => {
+                        return Ok(self
+                            .entries()?
+                            .iter()
+                            .filter_map(|entry| match entry {
+                                Entry::Operation(operation) => Some(operation.number),
+                                _ => None,
+                            })
+                            .max()
+                            .unwrap_or(0));
+                    },
+                }
+            }
+
+            tail.truncate(first_whole);
+        }
+
+        Ok(0)
+    }
+
+    pub(crate) fn record_undo(&mut self, number: u32) -> Result<(), TasksError> {
+        self.append(&[join(&["undo", &number.to_string(), &format::timestamp(Utc::now())])])
+    }
+
+    pub(crate) fn record_redo(&mut self, number: u32) -> Result<(), TasksError> {
+        self.append(&[join(&["redo", &number.to_string(), &format::timestamp(Utc::now())])])
+    }
+
+    /// Appends `lines` with a single synced write, starting the file with a
+    /// header if it is new.
+    fn append(&mut self, lines: &[String]) -> Result<(), TasksError> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+
+        let mut content = String::new();
+        if file.metadata()?.len() == 0 {
+            content.push_str(HEADER);
+            content.push('\n');
+        }
+        for line in lines {
+            content.push_str(line);
+            content.push('\n');
+        }
+
+        file.write_all(content.as_bytes())?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+}
+
+fn join(fields: &[&str]) -> String {
+    fields.iter().map(|field| format::escape(field)).collect::<Vec<_>>().join(&SEPARATOR.to_string())
+}
+
+fn parse_number(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("invalid operation number {value:?}"))
+}
@@ -1,185 +1,1863 @@
-use std::error::Error;
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufRead, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde_json::{json, Value};
+
+mod args;
+mod color;
+mod config;
+mod dates;
+mod due;
+mod editor;
+mod error;
+mod filter;
+mod format;
+mod journal;
+mod list;
+mod location;
+mod lock;
+mod output;
+mod priority;
+mod recurrence;
+mod store;
+mod table;
+mod task;
+
+pub use color::{ColorMode, Style, Theme};
+pub use config::Config;
+pub use dates::{Clock, FixedClock, SystemClock};
+pub use due::{Due, DueStatus};
+pub use error::TasksError;
+pub use filter::{Condition, Filter, Selector, TaskSpan};
+pub use journal::{Entry, Journal, Operation};
+pub use list::{Column, ListOptions, SortField, SortKey};
+pub use location::{DbLocation, DbSource};
+pub use lock::LockMode;
+pub use output::{error_document, JSON_VERSION};
+pub use priority::Priority;
+pub use recurrence::Recurrence;
+pub use store::file::FileStore;
+pub use store::memory::MemoryStore;
+#[cfg(feature = "sqlite")]
+pub use store::sqlite::SqliteStore;
+pub use store::TaskStore;
+pub use task::Task;
+
+use args::{Arg, Args};
+use config::{ARCHIVE_AFTER, CONFIG_SUFFIX, LIST_DEFAULT};
+use journal::JOURNAL_SUFFIX;
+use output::Output;
+use table::{Cell, Line, Table};
+use task::{parse_list, TAG_PREFIX};
 
-const FILENAME: &str = "/tmp/tasks.txt";
 const SEPARATOR: char = '|';
+const POSITION_PREFIX: char = '%';
+/// Commands acting on more tasks than this ask for confirmation first.
+const CONFIRM_ABOVE: usize = 3;
+const ARCHIVE_SUFFIX: &str = ".archive";
+const TREE_INDENT: &str = "  ";
+const LOCAL_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+const LIST_SHORT_FLAG: &str = "-l";
+
+/// Global flags given before the command name.
+#[derive(Default)]
+pub struct Options {
+    pub db: Option<PathBuf>,
+    pub lock_timeout: Option<Duration>,
+    /// The named list to work in, given with `-l` or `--list`.
+    pub list: Option<String>,
+    /// Prints a single JSON document instead of text.
+    pub json: bool,
+    /// Whether `list` colors its table, given with `--color`.
+    pub color: ColorMode,
+}
+
+impl Options {
+    /// Parses the leading global flags, returning them along with the
+    /// remaining arguments starting at the command name.
+    pub fn build(args: &[String]) -> Result<(Options, &[String]), TasksError> {
+        let mut options = Options::default();
+        let mut args = Args::new(args.get(1..).unwrap_or_default());  // Discard program name
+
+        while args.at_flag() || args.at(LIST_SHORT_FLAG) {
+            let (flag, inline) = match args.next() {
+                Some(Arg::Flag(flag, inline)) => (flag, inline),
+                Some(Arg::Value(flag)) => (flag, None),
+                None => break,
+            };
+
+            match flag {
+                "--db" => options.db = Some(PathBuf::from(args.value(flag, inline)?)),
+                "--lock-timeout" => options.lock_timeout = Some(lock::parse_timeout(args.value(flag, inline)?)?),
+                "--json" if inline.is_none() => options.json = true,
+                "--color" => options.color = args.value(flag, inline)?.parse()?,
+                "--list" | LIST_SHORT_FLAG => options.list = Some(parse_list(args.value(flag, inline)?)?),
+                _ => return Err(TasksError::UnknownFlag(flag.to_string())),
+            }
+        }
+
+        Ok((options, args.rest()))
+    }
+
+    /// Whether `args` ask for JSON output, for reporting errors found before
+    /// or while building the options.
+    pub fn json_requested(args: &[String]) -> bool {
+        args.iter().skip(1).take_while(|arg| *arg != "--").any(|arg| arg == "--json")
+    }
+}
 
 pub enum Command<'a> {
-    Add(&'a str),
-    List,
-    Complete(usize),
-    Delete(usize),
+    /// Adds the task, as a subtask of the referenced one if given; its id is
+    /// assigned when it is stored.
+    Add(Task, Option<TaskRef>),
+    List(ListOptions),
+    Complete(Selection),
+    Delete(Selection),
+    /// Replaces the task's name; without text, opens an editor on it.
+    Edit(TaskRef, Option<String>),
+    Append(TaskRef, String),
+    Prepend(TaskRef, String),
+    Reopen(TaskRef),
+    /// Makes the task depend on the referenced tasks, or with `None`, on
+    /// none.
+    Depends(TaskRef, Option<Vec<TaskRef>>),
+    Due(TaskRef, Option<Due>),
+    Priority(TaskRef, Option<Priority>),
+    /// Makes the task recur by the rule, or with `None`, stops it recurring.
+    Recur(TaskRef, Option<Recurrence>),
+    /// Moves a recurring task's due date to its next occurrence without
+    /// completing it.
+    Skip(TaskRef),
+    Migrate(&'a str),
+    /// Reverts the last N operations recorded in the journal.
+    Undo(usize),
+    /// Replays the last N undone operations.
+    Redo(usize),
+    History,
+    /// Moves completed tasks to the archive; with a date, only those
+    /// completed on or before it.
+    Archive(Option<NaiveDate>),
+    /// Shows the database settings, one of them, or sets one; a value of
+    /// `none` removes the setting.
+    Config(Option<&'a str>, Option<&'a str>),
+    /// Moves the task and its subtasks to the named list.
+    Move(TaskRef, String),
+    Tags,
+    Projects,
+    Lists,
+    Where,
 }
 
 impl<'a> Command<'a> {
-    pub fn build(args: &'a [String]) -> Result<Command<'a>, Box<dyn Error>> {
-        let mut args = args.iter();
+    pub fn build(args: &'a [String]) -> Result<Command<'a>, TasksError> {
+        Self::build_with_clock(args, &SystemClock)
+    }
 
-        args.next().unwrap();  // Discard program name
+    /// Builds the command, resolving relative dates such as `tomorrow`
+    /// against `clock`.
+    pub fn build_with_clock(args: &'a [String], clock: &dyn Clock) -> Result<Command<'a>, TasksError> {
+        let mut args = Args::new(args);
 
-        let command = args
-            .next()
-            .map(|s| s.to_lowercase())
-            .ok_or("Missing command")?;
+        let command = args.required("command")?.to_lowercase();
 
-        match command.as_str() {
+        let command = match command.as_str() {
             "add" => {
-                let task = args.next().ok_or("Missing task")?;
-                Ok(Command::Add(task))
-            },
-            "list" => Ok(Command::List),
-            "complete" | "delete" => {
-                let number = args.next()
-                    .ok_or("Missing task number")?
-                    .parse::<usize>()
-                    .map_err(|_| "Non-integer number")?;
-
-                if command == "complete" {
-                    Ok(Command::Complete(number))
+                let mut words = Vec::new();
+                let mut due = None;
+                let mut priority = None;
+                let mut parent = None;
+                let mut recurrence = None;
+
+                while let Some(arg) = args.next() {
+                    match arg {
+                        Arg::Value(value) => words.push(value),
+                        Arg::Flag(flag @ "--parent", inline) => parent = Some(args.value(flag, inline)?.parse()?),
+                        Arg::Flag(flag @ "--due", inline) => due = Some(dates::parse(args.value(flag, inline)?, clock)?),
+                        Arg::Flag(flag @ "--priority", inline) => priority = Some(args.value(flag, inline)?.parse()?),
+                        Arg::Flag(flag @ "--recur", inline) => recurrence = Some(args.value(flag, inline)?.parse()?),
+                        Arg::Flag(flag, _) => return Err(TasksError::UnknownFlag(flag.to_string())),
+                    }
+                }
+
+                let mut task = Task::from_description(0, &words.join(" "));
+                if task.name().is_empty() {
+                    return Err(TasksError::MissingArgument("task"));
+                }
+                let due = match due {
+                    Some(due) => Some(due),
+                    None => first_due(recurrence.as_ref(), clock.today())?,
+                };
+                task.set_due(due);
+                task.set_priority(priority);
+                task.set_recurrence(recurrence);
+
+                Command::Add(task, parent)
+            },
+            "list" => Command::List(ListOptions::build(&mut args, clock)?),
+            "next" => Command::List(ListOptions::build_next(&mut args, clock)?),
+            "where" => Command::Where,
+            "tags" => Command::Tags,
+            "projects" => Command::Projects,
+            "lists" => Command::Lists,
+            "move" => {
+                let task_ref = args.required("task id")?.parse::<TaskRef>()?;
+                Command::Move(task_ref, parse_list(args.required("list")?)?)
+            },
+            "migrate" => Command::Migrate(args.required("source database")?),
+            "undo" | "redo" => {
+                let count = match args.next() {
+                    Some(Arg::Value(value)) => value.parse().ok().filter(|&count| count > 0).ok_or_else(|| {
+                        TasksError::InvalidArgument(format!("Invalid count {value:?}, expected a positive number"))
+                    })?,
+                    Some(Arg::Flag(flag, _)) => return Err(TasksError::UnknownFlag(flag.to_string())),
+                    None => 1,
+                };
+
+                if command == "undo" {
+                    Command::Undo(count)
                 } else {
-                    Ok(Command::Delete(number))
+                    Command::Redo(count)
                 }
             },
-            _ => Err("Unsupported command".into()),
+            "history" => Command::History,
+            "archive" => {
+                let mut cutoff = None;
+
+                while let Some(arg) = args.next() {
+                    match arg {
+                        Arg::Flag(flag @ "--older-than", inline) => {
+                            cutoff = Some(dates::ago(args.value(flag, inline)?, clock)?);
+                        },
+                        Arg::Flag(flag, _) => return Err(TasksError::UnknownFlag(flag.to_string())),
+                        Arg::Value(value) => return Err(TasksError::InvalidArgument(format!("Unexpected argument {value:?}"))),
+                    }
+                }
+
+                Command::Archive(cutoff)
+            },
+            "config" => {
+                let key = match args.next() {
+                    Some(Arg::Value(key)) => Some(key),
+                    Some(Arg::Flag(flag, _)) => return Err(TasksError::UnknownFlag(flag.to_string())),
+                    None => None,
+                };
+                let value = match (key, args.next()) {
+                    (Some(_), Some(Arg::Value(value))) => Some(value),
+                    (_, Some(Arg::Flag(flag, _))) => return Err(TasksError::UnknownFlag(flag.to_string())),
+                    _ => None,
+                };
+
+                Command::Config(key, value)
+            },
+            "complete" => Command::Complete(Selection::build(&mut args, clock)?),
+            "delete" => Command::Delete(Selection::build(&mut args, clock)?),
+            "edit" | "append" | "prepend" => {
+                let task_ref = args.required("task id")?.parse::<TaskRef>()?;
+                let text = build_text(&mut args)?;
+
+                match (command.as_str(), text) {
+                    ("edit", text) => Command::Edit(task_ref, text),
+                    (_, None) => return Err(TasksError::MissingArgument("text")),
+                    ("append", Some(text)) => Command::Append(task_ref, text),
+                    (_, Some(text)) => Command::Prepend(task_ref, text),
+                }
+            },
+            "reopen" => Command::Reopen(args.required("task id")?.parse()?),
+            "depends" => {
+                let task_ref = args.required("task id")?.parse::<TaskRef>()?;
+                match args.required("on")? {
+                    "on" => {},
+                    value => return Err(TasksError::InvalidArgument(format!("Unexpected argument {value:?}, expected on"))),
+                }
+                let dependencies = match args.required("task ids")? {
+                    "none" => None,
+                    value => Some(value.split(',').map(str::parse).collect::<Result<_, _>>()?),
+                };
+
+                Command::Depends(task_ref, dependencies)
+            },
+            "due" => {
+                let task_ref = args.required("task id")?.parse::<TaskRef>()?;
+                let due = match args.required("due date")? {
+                    "none" => None,
+                    value => Some(dates::parse(value, clock)?),
+                };
+
+                Command::Due(task_ref, due)
+            },
+            "priority" => {
+                let task_ref = args.required("task id")?.parse::<TaskRef>()?;
+                let priority = match args.required("priority")? {
+                    "none" => None,
+                    value => Some(value.parse()?),
+                };
+
+                Command::Priority(task_ref, priority)
+            },
+            "recur" => {
+                let task_ref = args.required("task id")?.parse::<TaskRef>()?;
+                let recurrence = match args.required("recurrence")? {
+                    "none" => None,
+                    value => Some(value.parse()?),
+                };
+
+                Command::Recur(task_ref, recurrence)
+            },
+            "skip" => Command::Skip(args.required("task id")?.parse()?),
+            _ => return Err(TasksError::UnknownCommand(command)),
+        };
+
+        args.finish()?;
+
+        Ok(command)
+    }
+
+    /// The lock needed to run this command, if it touches the database.
+    pub fn lock_mode(&self) -> Option<LockMode> {
+        match self {
+            Command::Add(..)
+            | Command::Complete(_)
+            | Command::Delete(_)
+            | Command::Edit(..)
+            | Command::Append(..)
+            | Command::Prepend(..)
+            | Command::Reopen(_)
+            | Command::Depends(..)
+            | Command::Due(..)
+            | Command::Priority(..)
+            | Command::Recur(..)
+            | Command::Skip(_)
+            | Command::Move(..)
+            | Command::Migrate(_)
+            | Command::Undo(_)
+            | Command::Redo(_)
+            | Command::Archive(_)
+            | Command::Config(_, Some(_)) => Some(LockMode::Exclusive),
+            Command::List(_)
+            | Command::History
+            | Command::Config(_, None)
+            | Command::Tags
+            | Command::Projects
+            | Command::Lists => Some(LockMode::Shared),
+            Command::Where => None,
+        }
+    }
+
+    /// Confines the command to the named list: added tasks go there, and
+    /// listings and filters only see tasks in it.
+    fn within(self, list: &str) -> Self {
+        match self {
+            Command::Add(mut task, parent) => {
+                task.set_list(Some(list.to_string()));
+                Command::Add(task, parent)
+            },
+            Command::List(options) => Command::List(options.within(list)),
+            Command::Complete(selection) => Command::Complete(selection.within(list)),
+            Command::Delete(selection) => Command::Delete(selection.within(list)),
+            command => command,
+        }
+    }
+
+    /// The command's name, as recorded in the journal.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Add(..) => "add",
+            Command::List(_) => "list",
+            Command::Complete(_) => "complete",
+            Command::Delete(_) => "delete",
+            Command::Edit(..) => "edit",
+            Command::Append(..) => "append",
+            Command::Prepend(..) => "prepend",
+            Command::Reopen(_) => "reopen",
+            Command::Depends(..) => "depends",
+            Command::Due(..) => "due",
+            Command::Priority(..) => "priority",
+            Command::Recur(..) => "recur",
+            Command::Skip(_) => "skip",
+            Command::Migrate(_) => "migrate",
+            Command::Undo(_) => "undo",
+            Command::Redo(_) => "redo",
+            Command::History => "history",
+            Command::Archive(_) => "archive",
+            Command::Config(..) => "config",
+            Command::Move(..) => "move",
+            Command::Tags => "tags",
+            Command::Projects => "projects",
+            Command::Lists => "lists",
+            Command::Where => "where",
         }
     }
 }
 
-struct Task {
-    name: String,
-    completed: bool,
+/// The due date given to a task without one when it starts recurring: the
+/// first occurrence from `today` for rules that follow a calendar.
+fn first_due(recurrence: Option<&Recurrence>, today: NaiveDate) -> Result<Option<Due>, TasksError> {
+    recurrence
+        .filter(|recurrence| recurrence.is_scheduled())
+        .map(|recurrence| Ok(Due::new(recurrence.first(today)?, None)))
+        .transpose()
 }
 
-impl Task {
-    fn new(name: String) -> Self {
-        Self {
-            name,
-            completed: false,
+/// Joins the remaining values into free text, if there are any.
+fn build_text(args: &mut Args) -> Result<Option<String>, TasksError> {
+    let mut words = Vec::new();
+
+    for arg in args.by_ref() {
+        match arg {
+            Arg::Value(value) => words.push(value),
+            Arg::Flag(flag, _) => return Err(TasksError::UnknownFlag(flag.to_string())),
+        }
+    }
+
+    Ok(Some(words.join(" ")).filter(|text| !text.trim().is_empty()))
+}
+
+/// The tasks a bulk command acts on, along with answers to the questions
+/// it would otherwise ask.
+pub struct Selection {
+    pub selector: Selector,
+    /// Skips the confirmation asked for large selections.
+    pub yes: bool,
+    /// What to do with subtasks of the selected tasks; asked when unset.
+    pub subtasks: Option<Subtasks>,
+}
+
+impl Selection {
+    fn build(args: &mut Args, clock: &dyn Clock) -> Result<Self, TasksError> {
+        let mut words = Vec::new();
+        let mut yes = false;
+        let mut subtasks = None;
+
+        while let Some(arg) = args.next() {
+            match arg {
+                Arg::Value(value) => words.push(value),
+                Arg::Flag("--yes", None) => yes = true,
+                Arg::Flag(flag @ "--subtasks", inline) => subtasks = Some(args.value(flag, inline)?.parse()?),
+                Arg::Flag(flag, _) => return Err(TasksError::UnknownFlag(flag.to_string())),
+            }
         }
+
+        Ok(Self {
+            selector: Selector::parse(&words, clock)?,
+            yes,
+            subtasks,
+        })
     }
 
-    fn complete(&mut self) {
-        self.completed = true;
+    fn within(self, list: &str) -> Self {
+        Self { selector: self.selector.within(list), ..self }
     }
+}
 
-    fn from_string(content: &str) -> Result<Self, Box<dyn Error>> {
-        let parts: Vec<&str> = content.split('|').collect();
+/// How a command treats subtasks of the tasks it acts on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Subtasks {
+    /// Completes or deletes them as well.
+    Cascade,
+    /// Leaves them open, or when their parent is deleted, moves them up to
+    /// its parent.
+    Keep,
+}
+
+impl FromStr for Subtasks {
+    type Err = TasksError;
 
-        if !parts.len() == 2 {
-            return Err("Failed to parse Task".into());
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "cascade" => Ok(Subtasks::Cascade),
+            "keep" => Ok(Subtasks::Keep),
+            _ => Err(TasksError::InvalidArgument(format!("Invalid subtask mode {value:?}, expected cascade or keep"))),
         }
+    }
+}
+
+/// How a command names a task: by its stable id (`4`), or by its current
+/// position in the list with a `%` prefix (`%0`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaskRef {
+    Id(u32),
+    Position(usize),
+}
+
+impl FromStr for TaskRef {
+    type Err = TasksError;
 
-        let name = parts[1].to_string();
-        let completed = parts[0].parse::<usize>().expect("Not a number") == 1;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let task_ref = match value.strip_prefix(POSITION_PREFIX) {
+            Some(position) => position.parse().ok().map(TaskRef::Position),
+            None => value.parse().ok().filter(|&id| id > 0).map(TaskRef::Id),
+        };
 
-        Ok(Self { name, completed })
+        task_ref.ok_or_else(|| {
+            TasksError::InvalidArgument(format!(
+                "Invalid task id {value:?}, expected an id like 4 or a position like {POSITION_PREFIX}0",
+            ))
+        })
     }
+}
 
-    fn to_string(&self) -> String {
-        let completed = if self.completed { 1 } else { 0 };
-        format!(
-            "{}{}{}",
-            completed,
-            SEPARATOR,
-            self.name,
-        )
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskRef::Id(id) => write!(f, "{id}"),
+            TaskRef::Position(position) => write!(f, "{POSITION_PREFIX}{position}"),
+        }
     }
 }
 
-struct TaskList {
-    db_file: File,
+/// Runs `command` against the database selected by `options`, which is what
+/// the `todos` binary does.
+pub fn run_cli(options: &Options, command: Command) -> Result<(), TasksError> {
+    let location = DbLocation::resolve(options.db.as_deref())?;
+
+    if let Command::Where = command {
+        let mut out = Output::new(options.json);
+        print_location(&mut out, &location)?;
+        out.finish(command.name());
+        return Ok(());
+    }
+
+    let lock_timeout = lock::lock_timeout(options.lock_timeout)?;
+    let mut store = store::open(location.path, lock_timeout)?;
+
+    run_with_options(command, store.as_mut(), options)
+}
+
+/// Runs `command` against any task store.
+pub fn run(command: Command, store: &mut dyn TaskStore) -> Result<(), TasksError> {
+    run_with_options(command, store, &Options::default())
+}
+
+/// Runs `command` with the global options that apply to any store: the
+/// output format, and the list to work in, which falls back to the one set
+/// as `list.default`. Without either, commands see tasks in all lists.
+pub fn run_with_options(command: Command, store: &mut dyn TaskStore, options: &Options) -> Result<(), TasksError> {
+    let Some(lock_mode) = command.lock_mode() else {
+        return Err(TasksError::Unsupported("The where command is only available through run_cli".to_string()));
+    };
+
+    // The editor may stay open for as long as the user likes, so it runs
+    // before the lock is taken.
+    let edited = match &command {
+        Command::Edit(task_ref, None) => Some(edit_unlocked(store, *task_ref)?),
+        _ => None,
+    };
+
+    store.lock(lock_mode)?;
+
+    let mut task_list = TaskList::load(store)?;
+    let mut out = Output::new(options.json);
+    for warning in task_list.store.take_warnings() {
+        out.warn(warning);
+    }
+    let list = match &options.list {
+        Some(list) => Some(list.clone()),
+        None => task_list.config()?.get(LIST_DEFAULT).map(str::to_string),
+    };
+    let command = match &list {
+        Some(list) => command.within(list),
+        None => command,
+    };
+    let name = command.name();
+    let changes_tasks = lock_mode == LockMode::Exclusive && !matches!(command, Command::Config(..));
+    // Undo and redo must not archive the tasks they just restored.
+    let archives_automatically =
+        changes_tasks && !matches!(command, Command::Undo(_) | Command::Redo(_) | Command::Archive(_));
+
+    match command {
+        Command::Add(task, parent) => add_task(&mut out, &mut task_list, task, parent),
+        Command::List(list_options) => {
+            let config = task_list.config()?;
+            let columns = list_options.columns(&config)?;
+            let theme = match !options.json && options.color.enabled() {
+                true => Theme::load(&config)?,
+                false => Theme::default(),
+            };
+            match list_options.archived {
+                true => list_tasks(&mut out, &task_list.archived()?, &list_options, &columns, &theme),
+                false => list_tasks(&mut out, &task_list.tasks, &list_options, &columns, &theme),
+            }
+        },
+        Command::Complete(selection) => complete_tasks(&mut out, &mut task_list, &selection),
+        Command::Delete(selection) => delete_tasks(&mut out, &mut task_list, &selection),
+        Command::Edit(task_ref, text) => edit_task(&mut out, &mut task_list, task_ref, text, edited),
+        Command::Append(task_ref, text) => change_text(&mut out, &mut task_list, task_ref, |task| task.append(&text)),
+        Command::Prepend(task_ref, text) => change_text(&mut out, &mut task_list, task_ref, |task| task.prepend(&text)),
+        Command::Reopen(task_ref) => reopen_task(&mut out, &mut task_list, task_ref),
+        Command::Depends(task_ref, dependencies) => set_dependencies(&mut out, &mut task_list, task_ref, dependencies),
+        Command::Due(task_ref, due) => set_due(&mut out, &mut task_list, task_ref, due),
+        Command::Priority(task_ref, priority) => set_priority(&mut out, &mut task_list, task_ref, priority),
+        Command::Recur(task_ref, recurrence) => set_recurrence(&mut out, &mut task_list, task_ref, recurrence),
+        Command::Skip(task_ref) => skip_occurrence(&mut out, &mut task_list, task_ref),
+        Command::Tags => summarize(&mut out, &task_list, "Tag", |task| task.tags().to_vec()),
+        Command::Projects => summarize(&mut out, &task_list, "Project", |task| task.project().map(str::to_string).into_iter().collect()),
+        Command::Lists => summarize(&mut out, &task_list, "List", |task| vec![task.list().to_string()]),
+        Command::Move(task_ref, list) => move_task(&mut out, &mut task_list, task_ref, list),
+        Command::Migrate(source) => migrate_tasks(&mut out, &mut task_list, Path::new(source)),
+        Command::Undo(count) => undo(&mut out, &mut task_list, count),
+        Command::Redo(count) => redo(&mut out, &mut task_list, count),
+        Command::History => print_history(&mut out, &task_list),
+        Command::Archive(cutoff) => archive_tasks(&mut out, &mut task_list, cutoff),
+        Command::Config(key, value) => configure(&mut out, &task_list, key, value),
+        Command::Where => unreachable!("rejected before loading"),
+    }?;
+
+    if changes_tasks {
+        report_changes(&mut out, &task_list);
+    }
+    task_list.record(name)?;
+
+    if archives_automatically {
+        archive_by_policy(&mut out, &mut task_list)?;
+    }
+
+    out.finish(name);
+
+    Ok(())
+}
+
+/// Sets the document fields of a command that changes tasks: the changed
+/// tasks as they are now, unless the command reported them itself, and the
+/// ids of added and deleted tasks.
+fn report_changes(out: &mut Output, task_list: &TaskList) {
+    let before: HashSet<u32> = task_list.before.iter().map(Task::id).collect();
+    let after: HashSet<u32> = task_list.after.iter().map(Task::id).collect();
+
+    if !out.has("tasks") {
+        // A task changed twice is reported once, as it was last.
+        let mut seen = HashSet::new();
+        let mut changed: Vec<&Task> = task_list.after.iter().rev().filter(|task| seen.insert(task.id())).collect();
+        changed.reverse();
+        out.set("tasks", output::tasks_json(changed));
+    }
+
+    let mut added: Vec<u32> = after.difference(&before).copied().collect();
+    let mut deleted: Vec<u32> = before.difference(&after).copied().collect();
+    added.sort_unstable();
+    deleted.sort_unstable();
+    out.set("added", json!(added));
+    out.set("deleted", json!(deleted));
+}
+
+/// Tasks loaded from a store, forwarding each change back to it and
+/// remembering the changed tasks for the journal.
+struct TaskList<'a> {
+    store: &'a mut dyn TaskStore,
     tasks: Vec<Task>,
+    /// Changed tasks as they were when loaded, and as they are now.
+    before: Vec<Task>,
+    after: Vec<Task>,
 }
 
-impl TaskList {
-    fn load(db_file: File) -> Result<Self, Box<dyn Error>> {
-        let reader = io::BufReader::new(&db_file);
+impl<'a> TaskList<'a> {
+    /// Loads the tasks, numbering any that have no id yet.
+    fn load(store: &'a mut dyn TaskStore) -> Result<Self, TasksError> {
+        let mut task_list = Self {
+            tasks: store.load()?,
+            store,
+            before: Vec::new(),
+            after: Vec::new(),
+        };
+
+        let first_id = task_list.next_id();
+        number_tasks(&mut task_list.tasks, first_id);
+
+        Ok(task_list)
+    }
 
-        let mut tasks: Vec<Task> = Vec::new();
+    fn next_id(&self) -> u32 {
+        self.tasks.iter().map(Task::id).max().unwrap_or(0) + 1
+    }
 
-        for line in reader.lines() {
-            let content = line?;
-            let task = Task::from_string(&content)?;
+    /// Finds the position of the referenced task.
+    fn find(&self, task_ref: TaskRef) -> Result<usize, TasksError> {
+        let index = match task_ref {
+            TaskRef::Id(id) => self.tasks.iter().position(|task| task.id() == id),
+            TaskRef::Position(position) => Some(position).filter(|&position| position < self.tasks.len()),
+        };
 
-            tasks.push(task);
+        index.ok_or(TasksError::TaskNotFound(task_ref))
+    }
+
+    /// Finds the positions of the selected tasks, in list order and each
+    /// only once.
+    fn select(&self, selector: &Selector) -> Result<Vec<usize>, TasksError> {
+        let spans = match selector {
+            Selector::Tasks(spans) => spans,
+            Selector::Filter(filter) => {
+                return Ok((0..self.tasks.len()).filter(|&index| filter.matches(&self.tasks[index])).collect());
+            },
+        };
+
+        let mut indexes = Vec::new();
+        for span in spans {
+            match *span {
+                TaskSpan::Task(task_ref) => indexes.push(self.find(task_ref)?),
+                TaskSpan::Ids(start, end) => {
+                    indexes.extend((0..self.tasks.len()).filter(|&index| (start..=end).contains(&self.tasks[index].id())));
+                },
+            }
         }
+        indexes.sort_unstable();
+        indexes.dedup();
+
+        Ok(indexes)
+    }
+
+    fn add(&mut self, mut task: Task) -> Result<&Task, TasksError> {
+        task.stamp_created(Utc::now());
+        self.after.push(task.clone());
+        self.tasks.push(task);
+        self.store.insert(&self.tasks, self.tasks.len() - 1)?;
+
+        Ok(&self.tasks[self.tasks.len() - 1])
+    }
+
+    fn update(&mut self, index: usize, change: impl FnOnce(&mut Task)) -> Result<&Task, TasksError> {
+        let was_completed = self.tasks[index].is_completed();
+        self.before.push(self.tasks[index].clone());
+        change(&mut self.tasks[index]);
+        self.tasks[index].stamp_modified(Utc::now(), was_completed);
+        self.after.push(self.tasks[index].clone());
+        self.store.update(&self.tasks, index)?;
 
-        Ok(Self { db_file, tasks })
+        Ok(&self.tasks[index])
     }
 
-    fn save(&mut self) -> Result<(), Box<dyn Error>> {
-        self.db_file.set_len(0)?;
-        self.db_file.seek(SeekFrom::Start(0))?;
+    /// Finds a chain of dependencies leading from task `from` to task `to`,
+    /// returning the ids along it, from `from` to `to`.
+    fn dependency_path(&self, from: u32, to: u32) -> Option<Vec<u32>> {
+        let dependencies: HashMap<u32, &[u32]> = self.tasks.iter().map(|task| (task.id(), task.dependencies())).collect();
+        let mut previous: HashMap<u32, u32> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(id) = queue.pop_front() {
+            if id == to {
+                let mut path = vec![to];
+                while let Some(&before) = previous.get(path.last()?) {
+                    path.push(before);
+                }
+                path.reverse();
+                return Some(path);
+            }
 
-        for task in self.tasks.iter() {
-            writeln!(self.db_file, "{}", task.to_string())?;
+            for &next in dependencies.get(&id).copied().unwrap_or_default() {
+                if next != from && !previous.contains_key(&next) {
+                    previous.insert(next, id);
+                    queue.push_back(next);
+                }
+            }
         }
 
-        self.db_file.flush()?;
+        None
+    }
+
+    /// Finds the positions of all subtasks, direct or nested, of the tasks
+    /// at `indexes`, leaving out those tasks themselves.
+    fn descendants(&self, indexes: &[usize]) -> Vec<usize> {
+        let mut ids: HashSet<u32> = indexes.iter().map(|&index| self.tasks[index].id()).collect();
+        let mut found = Vec::new();
+
+        loop {
+            let children: Vec<usize> = (0..self.tasks.len())
+                .filter(|&index| {
+                    let task = &self.tasks[index];
+                    !ids.contains(&task.id()) && task.parent().is_some_and(|parent| ids.contains(&parent))
+                })
+                .collect();
+
+            if children.is_empty() {
+                found.sort_unstable();
+                return found;
+            }
+
+            ids.extend(children.iter().map(|&index| self.tasks[index].id()));
+            found.extend(children);
+        }
+    }
+
+    /// Changes the tasks at `indexes`, writing them back in one save.
+    fn update_all(&mut self, indexes: &[usize], change: impl Fn(&mut Task)) -> Result<(), TasksError> {
+        self.update_and_add(indexes, change, Vec::new())
+    }
+
+    /// Changes the tasks at `indexes` and appends the `added` ones, which
+    /// already have their ids, writing everything back in one save.
+    fn update_and_add(&mut self, indexes: &[usize], change: impl Fn(&mut Task), added: Vec<Task>) -> Result<(), TasksError> {
+        if let ([index], true) = (indexes, added.is_empty()) {
+            return self.update(*index, change).map(|_| ());
+        }
+
+        let now = Utc::now();
+        self.change_all(indexes, change, now);
+        for mut task in added {
+            task.stamp_created(now);
+            self.after.push(task.clone());
+            self.tasks.push(task);
+        }
+        self.store.save(&self.tasks)
+    }
+
+    /// Changes the tasks at `indexes` in memory and journals them, leaving
+    /// the save to the caller.
+    fn change_all(&mut self, indexes: &[usize], change: impl Fn(&mut Task), now: DateTime<Utc>) {
+        for &index in indexes {
+            let was_completed = self.tasks[index].is_completed();
+            self.before.push(self.tasks[index].clone());
+            change(&mut self.tasks[index]);
+            self.tasks[index].stamp_modified(now, was_completed);
+            self.after.push(self.tasks[index].clone());
+        }
+    }
+
+    fn remove(&mut self, index: usize) -> Result<Task, TasksError> {
+        let task = self.tasks.remove(index);
+        self.before.push(task.clone());
+        self.store.delete(&self.tasks, &task)?;
+
+        Ok(task)
+    }
+
+    /// Changes the tasks at `indexes`, then removes the tasks at the sorted
+    /// `removed` ones, all referring to the list before any removal, writing
+    /// the rest back in one save.
+    fn update_and_remove(&mut self, indexes: &[usize], change: impl Fn(&mut Task), removed: &[usize]) -> Result<Vec<Task>, TasksError> {
+        if let ([], [index]) = (indexes, removed) {
+            return Ok(vec![self.remove(*index)?]);
+        }
+
+        self.change_all(indexes, change, Utc::now());
+
+        let mut removed: Vec<Task> = removed.iter().rev().map(|&index| self.tasks.remove(index)).collect();
+        removed.reverse();
+        self.before.extend(removed.iter().cloned());
+        self.store.save(&self.tasks)?;
+
+        Ok(removed)
+    }
+
+    fn replace(&mut self, tasks: Vec<Task>) -> Result<(), TasksError> {
+        self.before.append(&mut self.tasks);
+        self.after.extend(tasks.iter().cloned());
+        self.tasks = tasks;
+        self.store.save(&self.tasks)
+    }
+
+    /// Swaps the `current` versions of tasks for their `restored` ones,
+    /// keeping the list in id order, and saves. Used to undo and redo, so
+    /// the swap is not itself journaled.
+    ///
+    /// Restored tasks the archive policy has moved away since are taken out
+    /// of the archive, after the save so a failure can leave a task in both
+    /// files but never in neither.
+    fn swap(&mut self, current: &[Task], restored: &[Task]) -> Result<(), TasksError> {
+        self.tasks.retain(|task| !current.iter().any(|current| current.id() == task.id()));
+
+        for task in restored {
+            let index = self.tasks.partition_point(|other| other.id() < task.id());
+            self.tasks.insert(index, task.clone());
+        }
+
+        self.store.save(&self.tasks)?;
+
+        let mut archived = self.archived()?;
+        let archived_count = archived.len();
+        archived.retain(|task| !restored.iter().any(|restored| restored.id() == task.id()));
+        if archived.len() < archived_count {
+            self.archive_store()?.save(&archived)?;
+        }
+
+        Ok(())
+    }
+
+    fn archive_store(&self) -> Result<FileStore, TasksError> {
+        match self.store.sidecar_path(ARCHIVE_SUFFIX) {
+            Some(path) => Ok(FileStore::new(path)),
+            None => Err(TasksError::Unsupported("This store keeps no archive".to_string())),
+        }
+    }
+
+    /// Loads the archived tasks, if the store can have any.
+    fn archived(&self) -> Result<Vec<Task>, TasksError> {
+        match self.store.sidecar_path(ARCHIVE_SUFFIX) {
+            Some(path) => FileStore::new(path).load(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// The id for a new task, never reusing the id of a deleted or archived
+    /// one.
+    fn next_free_id(&self) -> Result<u32, TasksError> {
+        let archived_next = self.archived()?.iter().map(Task::id).max().unwrap_or(0) + 1;
+
+        Ok(self.next_id().max(self.store.next_id()?).max(archived_next))
+    }
+
+    /// Moves the tasks at the sorted `indexes` to the end of the archive.
+    /// The archive is saved first, so a failure can leave a task in both
+    /// files but never in neither. Archiving is not journaled.
+    fn archive(&mut self, indexes: &[usize]) -> Result<Vec<Task>, TasksError> {
+        let mut archive = self.archive_store()?;
+        let mut archived = archive.load()?;
+
+        let moved: Vec<Task> = indexes.iter().map(|&index| self.tasks[index].clone()).collect();
+        archived.retain(|task| !moved.iter().any(|moved| moved.id() == task.id()));
+        archived.extend(moved.iter().cloned());
+        archive.save(&archived)?;
+
+        for &index in indexes.iter().rev() {
+            self.tasks.remove(index);
+        }
+        self.store.save(&self.tasks)?;
+
+        Ok(moved)
+    }
+
+    fn config(&self) -> Result<Config, TasksError> {
+        Config::load(self.store.sidecar_path(CONFIG_SUFFIX))
+    }
+
+    fn journal(&self) -> Result<Journal, TasksError> {
+        match self.store.sidecar_path(JOURNAL_SUFFIX) {
+            Some(path) => Ok(Journal::new(path)),
+            None => Err(TasksError::Unsupported("This store keeps no journal to undo from".to_string())),
+        }
+    }
+
+    /// Appends the changes made by `command` to the journal, if any.
+    fn record(&mut self, command: &str) -> Result<(), TasksError> {
+        if self.before.is_empty() && self.after.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(path) = self.store.sidecar_path(JOURNAL_SUFFIX) {
+            Journal::new(path).record(command, &self.before, &self.after)?;
+        }
 
         Ok(())
     }
 }
 
-pub fn run(command: Command) -> Result<(), Box<dyn Error>> {
-    let db_path = Path::new(FILENAME);
-    let db_file = OpenOptions::new()
-        .create(true)
-        .read(true)
-        .write(true)
-        .open(&db_path)?;
+/// Appends `suffix` to the file name of `path`, e.g. `tasks.txt.bak`.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
 
-    let mut task_list = TaskList::load(db_file)?;
+fn print_location(out: &mut Output, location: &DbLocation) -> Result<(), TasksError> {
+    out.text(location.path.display().to_string());
+    out.text(format!("source: {}", location.source));
+    out.set("path", json!(location.path));
+    out.set("source", json!(location.source.to_string()));
 
-    match command {
-        Command::Add(content) => add_task(&mut task_list, &content),
-        Command::List => list_tasks(&task_list),
-        Command::Complete(number) => complete_task(&mut task_list, number),
-        Command::Delete(number) => delete_task(&mut task_list, number),
+    Ok(())
+}
+
+fn add_task(out: &mut Output, task_list: &mut TaskList, mut task: Task, parent: Option<TaskRef>) -> Result<(), TasksError> {
+    task.set_id(task_list.next_free_id()?);
+
+    // Subtasks live in their parent's list.
+    if let Some(parent) = parent {
+        let parent = &task_list.tasks[task_list.find(parent)?];
+        task.set_parent(Some(parent.id()));
+        task.set_list(Some(parent.list().to_string()));
+    }
+
+    let task = task_list.add(task)?;
+
+    match task.parent() {
+        Some(parent) => out.say(format!("Added task {} under task {parent}: {}", task.id(), task.name())),
+        None => out.say(format!("Added task {}: {}", task.id(), task.name())),
+    }
+
+    Ok(())
+}
+
+fn list_tasks(
+    out: &mut Output,
+    all_tasks: &[Task],
+    options: &ListOptions,
+    columns: &[Column],
+    theme: &Theme,
+) -> Result<(), TasksError> {
+    let now = SystemClock.now();
+    let now_utc = Utc::now();
+
+    // Completed and total subtasks per parent, counted over all tasks.
+    let mut progress: HashMap<u32, (usize, usize)> = HashMap::new();
+    for task in all_tasks {
+        if let Some(parent) = task.parent() {
+            let (done, total) = progress.entry(parent).or_default();
+            *done += usize::from(task.is_completed());
+            *total += 1;
+        }
+    }
+
+    let open: HashSet<u32> = all_tasks.iter().filter(|task| !task.is_completed()).map(Task::id).collect();
+    let blockers = |task: &Task| -> Vec<u32> {
+        task.dependencies().iter().copied().filter(|dependency| open.contains(dependency)).collect()
+    };
+
+    let mut tasks: Vec<&Task> = all_tasks
+        .iter()
+        .filter(|task| options.matches(task, now))
+        .filter(|task| !options.actionable || (!task.is_completed() && blockers(task).is_empty()))
+        .collect();
+    options.sort(&mut tasks);
+
+    // Tasks from more than one list are shown under a heading per list.
+    let mut groups: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+    for task in tasks {
+        groups.entry(task.list()).or_default().push(task);
+    }
+
+    let headings = columns.iter().map(|column| column.heading()).collect();
+    let flexible = columns.iter().position(|&column| column == Column::Task);
+    let mut table = Table::new(headings, flexible);
+    if groups.len() <= 1 {
+        table.push(Line::Header);
+    }
+
+    let mut shown = Vec::new();
+    for (group, (list_name, tasks)) in groups.iter().enumerate() {
+        if groups.len() > 1 {
+            if group > 0 {
+                table.push(Line::Text(String::new()));
+            }
+            table.push(Line::Text(format!("[{list_name}]")));
+            table.push(Line::Header);
+        }
+
+        for (task, depth) in list::tree(tasks) {
+            let plain = Style::default();
+            let cell = |column: &Column| -> Cell {
+                let (text, style) = match column {
+                    Column::Id => (task.id().to_string(), &plain),
+                    Column::Status => (if task.is_completed() { "[x]" } else { "[ ]" }.to_string(), &plain),
+                    Column::Priority => match task.priority() {
+                        Some(priority) => (priority.to_string(), theme.priority(priority)),
+                        None => (String::new(), &plain),
+                    },
+                    Column::Due => match task.due() {
+                        Some(due) if !task.is_completed() => match due.status(now) {
+                            DueStatus::Overdue => (format!("{due} (overdue)"), &theme.overdue),
+                            DueStatus::Today => (format!("{due} (today)"), &theme.today),
+                            DueStatus::Upcoming => (due.to_string(), &plain),
+                        },
+                        Some(due) => (due.to_string(), &plain),
+                        None => (String::new(), &plain),
+                    },
+                    Column::Project => (task.project().unwrap_or_default().to_string(), &plain),
+                    Column::Tags => {
+                        let tags: Vec<String> = task.tags().iter().map(|tag| format!("{TAG_PREFIX}{tag}")).collect();
+                        (tags.join(" "), &theme.tag)
+                    },
+                    Column::List => (task.list().to_string(), &plain),
+                    Column::Created => {
+                        let created = task
+                            .created()
+                            .map(|created| created.with_timezone(&Local).format(LOCAL_TIME_FORMAT).to_string());
+                        (created.unwrap_or_default(), &plain)
+                    },
+                    Column::Age => {
+                        let age = match (task.completed_at(), task.created()) {
+                            (Some(completed_at), _) if task.is_completed() => {
+                                list::format_completed(completed_at, now.date())
+                            },
+                            (_, Some(created)) => list::format_age(created, now_utc),
+                            _ => String::new(),
+                        };
+                        (age, &plain)
+                    },
+                    Column::Task => {
+                        let mut name = format!("{}{}", TREE_INDENT.repeat(depth), task.name());
+                        if let Some((done, total)) = progress.get(&task.id()) {
+                            name.push_str(&format!(" ({done}/{total})"));
+                        }
+                        if let Some(recurrence) = task.recurrence() {
+                            name.push_str(&format!(" (repeats {recurrence})"));
+                        }
+                        let blocked_by = blockers(task);
+                        if !task.is_completed() && !blocked_by.is_empty() {
+                            name.push_str(&format!(" (blocked by {})", format_ids(&blocked_by)));
+                        }
+                        (name, &plain)
+                    },
+                };
+
+                // Completed tasks are dimmed as a whole, whatever their cells.
+                let style = if task.is_completed() { &theme.completed } else { style };
+                Cell::new(text, style.clone())
+            };
+
+            table.push(Line::Row(columns.iter().map(cell).collect()));
+            shown.push(task);
+        }
+    }
+
+    for line in table.render(table::terminal_width(), options.wrap) {
+        out.text(line);
+    }
+    out.set("tasks", output::tasks_json(shown));
+
+    Ok(())
+}
+
+/// Asks `question` on the terminal, returning the answer trimmed and in
+/// lower case.
+fn ask(question: &str) -> Result<String, TasksError> {
+    print!("{question} ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    Ok(answer.trim().to_lowercase())
+}
+
+fn print_task_lines(task_list: &TaskList, indexes: &[usize]) {
+    for &index in indexes {
+        let task = &task_list.tasks[index];
+        println!("  {}: {}", task.id(), task.name());
+    }
+}
+
+/// Asks on the terminal whether to `verb` the selected tasks, unless there
+/// are few of them and picked by id, or `yes` was given. Without a terminal
+/// to ask on, large or filtered selections need `yes`.
+fn confirm(out: &mut Output, task_list: &TaskList, indexes: &[usize], verb: &str, selection: &Selection) -> Result<bool, TasksError> {
+    let filtered = matches!(selection.selector, Selector::Filter(_));
+    if selection.yes || (indexes.len() <= CONFIRM_ABOVE && !filtered) || indexes.is_empty() {
+        return Ok(true);
+    }
+
+    if !out.can_ask() {
+        return Err(TasksError::InvalidArgument(format!(
+            "Refusing to {} {} tasks without confirmation, pass --yes",
+            verb.to_lowercase(),
+            indexes.len(),
+        )));
+    }
+
+    print_task_lines(task_list, indexes);
+    let answer = ask(&format!("{verb} these {} tasks? [y/N]", indexes.len()))?;
+
+    Ok(matches!(answer.as_str(), "y" | "yes"))
+}
+
+/// Decides what to do with the `subtasks` of the selected tasks: as given
+/// on the command line, or else by asking on the terminal. `None` means the
+/// user chose to abort.
+fn choose_subtasks(
+    out: &mut Output,
+    task_list: &TaskList,
+    subtasks: &[usize],
+    given: Option<Subtasks>,
+    question: &str,
+) -> Result<Option<Subtasks>, TasksError> {
+    if given.is_some() {
+        return Ok(given);
+    }
+
+    if !out.can_ask() {
+        return Err(TasksError::InvalidArgument(format!(
+            "The selected tasks have {} subtasks, pass --subtasks=cascade or --subtasks=keep",
+            subtasks.len(),
+        )));
+    }
+
+    print_task_lines(task_list, subtasks);
+    let answer = ask(question)?;
+
+    Ok(match answer.as_str() {
+        "c" | "cascade" => Some(Subtasks::Cascade),
+        "k" | "keep" => Some(Subtasks::Keep),
+        _ => None,
+    })
+}
+
+/// Completes the selected tasks. Open subtasks of a completed task are
+/// completed too or left open, as the selection says or the user answers.
+/// A recurring task hands its rule over to a new pending instance due on
+/// the next occurrence.
+fn complete_tasks(out: &mut Output, task_list: &mut TaskList, selection: &Selection) -> Result<(), TasksError> {
+    let mut indexes = task_list.select(&selection.selector)?;
+    if indexes.is_empty() {
+        out.say("No matching tasks");
+        return Ok(());
+    }
+
+    let open_subtasks: Vec<usize> = task_list
+        .descendants(&indexes)
+        .into_iter()
+        .filter(|&index| !task_list.tasks[index].is_completed())
+        .collect();
+
+    if !open_subtasks.is_empty() {
+        let question = "These subtasks are still open. [c]ascade and complete them too, [k]eep them open, or [A]bort?";
+        match choose_subtasks(out, task_list, &open_subtasks, selection.subtasks, question)? {
+            Some(Subtasks::Cascade) => {
+                indexes.extend(open_subtasks);
+                indexes.sort_unstable();
+            },
+            Some(Subtasks::Keep) => {},
+            None => {
+                out.say("Nothing changed");
+                return Ok(());
+            },
+        }
+    }
+
+    if !confirm(out, task_list, &indexes, "Complete", selection)? {
+        out.say("Nothing changed");
+        return Ok(());
+    }
+
+    warn_open_dependencies(out, task_list, &indexes);
+
+    // Pending recurring tasks come back as new tasks, saved along with the
+    // completions.
+    let today = SystemClock.today();
+    let next_occurrences: Vec<Task> = indexes
+        .iter()
+        .map(|&index| &task_list.tasks[index])
+        .filter(|task| !task.is_completed())
+        .filter_map(|task| task.next_occurrence(today).transpose())
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .zip(task_list.next_free_id()?..)
+        .map(|(mut next, id)| {
+            next.set_id(id);
+            next
+        })
+        .collect();
+    let added = next_occurrences.len();
+
+    task_list.update_and_add(
+        &indexes,
+        |task| {
+            task.complete();
+            task.set_recurrence(None);
+        },
+        next_occurrences,
+    )?;
+
+    for index in indexes {
+        let task = &task_list.tasks[index];
+        out.say(format!("Completed task {}: {}", task.id(), task.name()));
+    }
+
+    for next in &task_list.tasks[task_list.tasks.len() - added..] {
+        let due = next.due().map(|due| due.to_string()).unwrap_or_default();
+        out.say(format!("Next occurrence is task {}, due {due}: {}", next.id(), next.name()));
+    }
+
+    Ok(())
+}
+
+/// Warns about tasks at `indexes` depending on open tasks that are not
+/// being completed along with them.
+fn warn_open_dependencies(out: &mut Output, task_list: &TaskList, indexes: &[usize]) {
+    let completing: HashSet<u32> = indexes.iter().map(|&index| task_list.tasks[index].id()).collect();
+    let open: HashSet<u32> = task_list
+        .tasks
+        .iter()
+        .filter(|task| !task.is_completed() && !completing.contains(&task.id()))
+        .map(Task::id)
+        .collect();
+
+    for &index in indexes {
+        let task = &task_list.tasks[index];
+        let open_dependencies: Vec<u32> =
+            task.dependencies().iter().copied().filter(|dependency| open.contains(dependency)).collect();
+
+        if !open_dependencies.is_empty() {
+            out.warn(format!("task {} depends on open tasks {}", task.id(), format_ids(&open_dependencies)));
+        }
+    }
+}
+
+/// A task's name as changed in the editor, along with the task's
+/// modification time when the editor was opened.
+struct Edited {
+    id: u32,
+    modified: Option<DateTime<Utc>>,
+    text: String,
+}
+
+/// Opens the task's name in the editor without holding the database lock.
+fn edit_unlocked(store: &mut dyn TaskStore, task_ref: TaskRef) -> Result<Edited, TasksError> {
+    let task_list = TaskList::load(store)?;
+    let task = &task_list.tasks[task_list.find(task_ref)?];
+
+    Ok(Edited {
+        id: task.id(),
+        modified: task.modified(),
+        text: editor::edit(task.name())?,
+    })
+}
+
+/// Replaces the task's text with `text`, or with the name `edited` before
+/// the lock was taken, unless the task was modified in the meantime.
+fn edit_task(
+    out: &mut Output,
+    task_list: &mut TaskList,
+    task_ref: TaskRef,
+    text: Option<String>,
+    edited: Option<Edited>,
+) -> Result<(), TasksError> {
+    let (task_ref, text) = match (text, edited) {
+        (Some(text), _) => (task_ref, text),
+        (None, Some(edited)) => {
+            let task = &task_list.tasks[task_list.find(TaskRef::Id(edited.id))?];
+            if task.modified() != edited.modified {
+                return Err(TasksError::InvalidArgument(format!(
+                    "Task {} was changed while it was being edited, edit it again",
+                    task.id(),
+                )));
+            }
+            if edited.text == task.name() {
+                out.say("Nothing changed");
+                return Ok(());
+            }
+            (TaskRef::Id(edited.id), edited.text)
+        },
+        (None, None) => return Err(TasksError::MissingArgument("text")),
+    };
+
+    change_text(out, task_list, task_ref, |task| task.edit(&text))
+}
+
+/// Applies a change to the task's text, refusing to leave its name empty.
+fn change_text(out: &mut Output, task_list: &mut TaskList, task_ref: TaskRef, change: impl FnOnce(&mut Task)) -> Result<(), TasksError> {
+    let index = task_list.find(task_ref)?;
+
+    let mut changed = task_list.tasks[index].clone();
+    change(&mut changed);
+    if changed.name().is_empty() {
+        return Err(TasksError::InvalidArgument("A task name cannot be empty".to_string()));
     }
+
+    let task = task_list.update(index, |task| *task = changed)?;
+
+    out.say(format!("Edited task {}: {}", task.id(), task.name()));
+
+    Ok(())
 }
 
-fn add_task(task_list: &mut TaskList, content: &str) -> Result<(), Box<dyn Error>> {
-    println!("Adding task: {}", content);
+fn set_dependencies(
+    out: &mut Output,
+    task_list: &mut TaskList,
+    task_ref: TaskRef,
+    dependencies: Option<Vec<TaskRef>>,
+) -> Result<(), TasksError> {
+    let index = task_list.find(task_ref)?;
+    let id = task_list.tasks[index].id();
 
-    let task = Task::new(content.to_string());
+    let Some(dependencies) = dependencies else {
+        let task = task_list.update(index, Task::clear_dependencies)?;
+        out.say(format!("Cleared dependencies of task {}: {}", task.id(), task.name()));
+        return Ok(());
+    };
+
+    let mut ids = Vec::new();
+    for dependency in dependencies {
+        let dependency = task_list.tasks[task_list.find(dependency)?].id();
+        if let Some(path) = task_list.dependency_path(dependency, id) {
+            return Err(TasksError::InvalidArgument(format!(
+                "Task {id} cannot depend on task {dependency}, which would create the cycle {}",
+                format_cycle(id, &path),
+            )));
+        }
+        ids.push(dependency);
+    }
 
-    task_list.tasks.push(task);
-    task_list.save()?;
+    let task = task_list.update(index, |task| ids.iter().for_each(|&dependency| task.add_dependency(dependency)))?;
+    out.say(format!("Task {} depends on {}: {}", task.id(), format_ids(task.dependencies()), task.name()));
 
     Ok(())
 }
 
-fn list_tasks(task_list: &TaskList) -> Result<(), Box<dyn Error>> {
-    println!("#{SEPARATOR}C{SEPARATOR}Task");
+/// Formats a cycle starting at `id` and continuing along `path`, which
+/// ends back at `id`, as in `4 -> 3 -> 4`.
+fn format_cycle(id: u32, path: &[u32]) -> String {
+    std::iter::once(id).chain(path.iter().copied()).map(|id| id.to_string()).collect::<Vec<_>>().join(" -> ")
+}
+
+fn reopen_task(out: &mut Output, task_list: &mut TaskList, task_ref: TaskRef) -> Result<(), TasksError> {
+    let index = task_list.find(task_ref)?;
+    let task = task_list.update(index, Task::reopen)?;
+
+    out.say(format!("Reopened task {}: {}", task.id(), task.name()));
+
+    Ok(())
+}
+
+fn set_due(out: &mut Output, task_list: &mut TaskList, task_ref: TaskRef, due: Option<Due>) -> Result<(), TasksError> {
+    let index = task_list.find(task_ref)?;
+    let task = task_list.update(index, |task| task.set_due(due))?;
 
-    for (index, task) in task_list.tasks.iter().enumerate() {
-        println!("{}{}{}", index, SEPARATOR, task.to_string());
+    match task.due() {
+        Some(due) => out.say(format!("Task {} is due {due}: {}", task.id(), task.name())),
+        None => out.say(format!("Cleared due date of task {}: {}", task.id(), task.name())),
     }
 
     Ok(())
 }
 
-fn complete_task(task_list: &mut TaskList, number: usize) -> Result<(), Box<dyn Error>> {
-    println!("Completing task: {}", number);
+fn set_priority(out: &mut Output, task_list: &mut TaskList, task_ref: TaskRef, priority: Option<Priority>) -> Result<(), TasksError> {
+    let index = task_list.find(task_ref)?;
+    let task = task_list.update(index, |task| task.set_priority(priority))?;
 
-    task_list.tasks.get_mut(number).ok_or_else(|| "Missing task")?.complete();
-    task_list.save()?;
+    match task.priority() {
+        Some(priority) => out.say(format!("Task {} has priority {priority}: {}", task.id(), task.name())),
+        None => out.say(format!("Cleared priority of task {}: {}", task.id(), task.name())),
+    }
 
     Ok(())
 }
 
-fn delete_task(task_list: &mut TaskList, number: usize) -> Result<(), Box<dyn Error>> {
-    println!("Deleting task: {}", number);
+fn set_recurrence(out: &mut Output, task_list: &mut TaskList, task_ref: TaskRef, recurrence: Option<Recurrence>) -> Result<(), TasksError> {
+    let index = task_list.find(task_ref)?;
+    let due = match task_list.tasks[index].due() {
+        Some(due) => Some(due),
+        None => first_due(recurrence.as_ref(), SystemClock.today())?,
+    };
+
+    let task = task_list.update(index, |task| {
+        task.set_due(due);
+        task.set_recurrence(recurrence);
+    })?;
 
-    if task_list.tasks.len() < number {
-        return Err("Missing task".into());
+    match task.recurrence() {
+        Some(recurrence) => out.say(format!("Task {} repeats {recurrence}: {}", task.id(), task.name())),
+        None => out.say(format!("Task {} no longer repeats: {}", task.id(), task.name())),
     }
 
-    task_list.tasks.remove(number);
-    task_list.save()?;
+    Ok(())
+}
+
+fn skip_occurrence(out: &mut Output, task_list: &mut TaskList, task_ref: TaskRef) -> Result<(), TasksError> {
+    let index = task_list.find(task_ref)?;
+    let task = &task_list.tasks[index];
+    if task.is_completed() || task.recurrence().is_none() {
+        return Err(TasksError::InvalidArgument(format!("Task {} is not a pending recurring task", task.id())));
+    }
+
+    let skipped = task.due();
+    let due = task.next_occurrence(SystemClock.today())?.and_then(|next| next.due());
+    let task = task_list.update(index, |task| task.set_due(due))?;
+
+    let due = task.due().map(|due| due.to_string()).unwrap_or_default();
+    match skipped {
+        Some(skipped) => out.say(format!("Skipped {skipped}, task {} is now due {due}: {}", task.id(), task.name())),
+        None => out.say(format!("Task {} is now due {due}: {}", task.id(), task.name())),
+    }
+
+    Ok(())
+}
+
+/// Moves the task to the named list, along with its subtasks.
+fn move_task(out: &mut Output, task_list: &mut TaskList, task_ref: TaskRef, list: String) -> Result<(), TasksError> {
+    let index = task_list.find(task_ref)?;
+    let subtasks = task_list.descendants(&[index]);
+
+    let mut indexes = subtasks.clone();
+    indexes.push(index);
+    indexes.sort_unstable();
+    task_list.update_all(&indexes, |task| task.set_list(Some(list.clone())))?;
+
+    let task = &task_list.tasks[index];
+    out.say(format!("Moved task {} to list {}: {}", task.id(), task.list(), task.name()));
+    if !subtasks.is_empty() {
+        let mut ids: Vec<u32> = subtasks.iter().map(|&index| task_list.tasks[index].id()).collect();
+        ids.sort_unstable();
+        out.say(format!("Moved its subtasks along with it: {}", format_ids(&ids)));
+    }
+
+    Ok(())
+}
+
+/// Prints every tag, project or list with its open and closed task counts.
+fn summarize(out: &mut Output, task_list: &TaskList, heading: &str, keys: impl Fn(&Task) -> Vec<String>) -> Result<(), TasksError> {
+    let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+
+    for task in task_list.tasks.iter() {
+        for key in keys(task) {
+            let (open, closed) = counts.entry(key).or_default();
+            if task.is_completed() {
+                *closed += 1;
+            } else {
+                *open += 1;
+            }
+        }
+    }
+
+    out.text(format!("{heading}{SEPARATOR}Open{SEPARATOR}Closed"));
+
+    for (key, (open, closed)) in &counts {
+        out.text(format!("{key}{SEPARATOR}{open}{SEPARATOR}{closed}"));
+    }
+
+    let counts: Vec<Value> =
+        counts.iter().map(|(name, (open, closed))| json!({ "name": name, "open": open, "closed": closed })).collect();
+    out.set("counts", Value::Array(counts));
 
     Ok(())
 }
+
+/// Deletes the selected tasks. Their subtasks are deleted as well or moved
+/// up to the nearest remaining ancestor, as the selection says or the user
+/// answers.
+fn delete_tasks(out: &mut Output, task_list: &mut TaskList, selection: &Selection) -> Result<(), TasksError> {
+    let mut indexes = task_list.select(&selection.selector)?;
+    if indexes.is_empty() {
+        out.say("No matching tasks");
+        return Ok(());
+    }
+
+    let subtasks = task_list.descendants(&indexes);
+    let mut keep_subtasks = false;
+
+    if !subtasks.is_empty() {
+        let question = "These subtasks would lose their parent. [c]ascade and delete them too, \
+            [k]eep them under the next remaining parent, or [A]bort?";
+        match choose_subtasks(out, task_list, &subtasks, selection.subtasks, question)? {
+            Some(Subtasks::Cascade) => {
+                indexes.extend(subtasks);
+                indexes.sort_unstable();
+            },
+            Some(Subtasks::Keep) => keep_subtasks = true,
+            None => {
+                out.say("Nothing changed");
+                return Ok(());
+            },
+        }
+    }
+
+    if !confirm(out, task_list, &indexes, "Delete", selection)? {
+        out.say("Nothing changed");
+        return Ok(());
+    }
+
+    let new_parents = match keep_subtasks {
+        true => new_parents(task_list, &indexes),
+        false => HashMap::new(),
+    };
+    // Remaining tasks stop depending on the deleted ones in the same save.
+    let removed_ids: HashSet<u32> = indexes.iter().map(|&index| task_list.tasks[index].id()).collect();
+    let changed: Vec<usize> = (0..task_list.tasks.len())
+        .filter(|index| !indexes.contains(index))
+        .filter(|&index| {
+            let task = &task_list.tasks[index];
+            new_parents.contains_key(&task.id()) || task.dependencies().iter().any(|id| removed_ids.contains(id))
+        })
+        .collect();
+
+    let change = |task: &mut Task| {
+        if let Some(&parent) = new_parents.get(&task.id()) {
+            task.set_parent(parent);
+        }
+        removed_ids.iter().for_each(|&id| task.remove_dependency(id));
+    };
+    let removed = task_list.update_and_remove(&changed, change, &indexes)?;
+
+    for task in task_list.tasks.iter().filter(|task| new_parents.contains_key(&task.id())) {
+        match task.parent() {
+            Some(parent) => out.say(format!("Moved task {} under task {parent}: {}", task.id(), task.name())),
+            None => out.say(format!("Moved task {} to the top level: {}", task.id(), task.name())),
+        }
+    }
+    for task in removed {
+        out.say(format!("Deleted task {}: {}", task.id(), task.name()));
+    }
+
+    Ok(())
+}
+
+/// The new parents of the subtasks of the tasks at `removed`, by subtask
+/// id: their nearest ancestor that is not being removed.
+fn new_parents(task_list: &TaskList, removed: &[usize]) -> HashMap<u32, Option<u32>> {
+    let parents: HashMap<u32, Option<u32>> = removed
+        .iter()
+        .map(|&index| (task_list.tasks[index].id(), task_list.tasks[index].parent()))
+        .collect();
+
+    let mut new_parents = HashMap::new();
+
+    for task in &task_list.tasks {
+        let Some(mut parent) = task.parent().filter(|parent| parents.contains_key(parent)) else {
+            continue;
+        };
+        if parents.contains_key(&task.id()) {
+            continue;
+        }
+
+        let mut new_parent = None;
+        // The bound guards against parent cycles.
+        for _ in 0..=parents.len() {
+            match parents.get(&parent) {
+                Some(Some(grandparent)) => parent = *grandparent,
+                Some(None) => break,
+                None => {
+                    new_parent = Some(parent);
+                    break;
+                },
+            }
+        }
+
+        new_parents.insert(task.id(), new_parent);
+    }
+
+    new_parents
+}
+
+fn undo(out: &mut Output, task_list: &mut TaskList, count: usize) -> Result<(), TasksError> {
+    let mut journal = task_list.journal()?;
+    let (applied, _) = journal.stacks()?;
+    if applied.is_empty() {
+        return Err(TasksError::InvalidArgument("Nothing to undo".to_string()));
+    }
+
+    let mut restored = Vec::new();
+    for operation in applied.iter().rev().take(count) {
+        task_list.swap(&operation.after, &operation.before)?;
+        journal.record_undo(operation.number)?;
+        out.say(format!("Undid operation {}: {} {}", operation.number, operation.command, format_ids(&operation.task_ids())));
+        restored.extend(&operation.before);
+    }
+    report_swapped(out, applied.iter().rev().take(count), restored);
+
+    Ok(())
+}
+
+fn redo(out: &mut Output, task_list: &mut TaskList, count: usize) -> Result<(), TasksError> {
+    let mut journal = task_list.journal()?;
+    let (_, undone) = journal.stacks()?;
+    if undone.is_empty() {
+        return Err(TasksError::InvalidArgument("Nothing to redo".to_string()));
+    }
+
+    let mut restored = Vec::new();
+    for operation in undone.iter().rev().take(count) {
+        task_list.swap(&operation.before, &operation.after)?;
+        journal.record_redo(operation.number)?;
+        out.say(format!("Redid operation {}: {} {}", operation.number, operation.command, format_ids(&operation.task_ids())));
+        restored.extend(&operation.after);
+    }
+    report_swapped(out, undone.iter().rev().take(count), restored);
+
+    Ok(())
+}
+
+/// Sets the document fields of undo and redo, which swap tasks without the
+/// bookkeeping `report_changes` reads: the operations and the tasks as
+/// they were restored.
+fn report_swapped<'a>(out: &mut Output, operations: impl Iterator<Item = &'a Operation>, restored: Vec<&Task>) {
+    out.set("operations", json!(operations.map(|operation| operation.number).collect::<Vec<_>>()));
+    out.set("tasks", output::tasks_json(restored));
+}
+
+/// Prints the journaled operations, oldest first, marking undone ones.
+fn print_history(out: &mut Output, task_list: &TaskList) -> Result<(), TasksError> {
+    let journal = task_list.journal()?;
+    let (applied, _) = journal.stacks()?;
+
+    out.text(format!("#{SEPARATOR}When{SEPARATOR}Command{SEPARATOR}Tasks{SEPARATOR}State"));
+    let mut operations = Vec::new();
+
+    for entry in journal.entries()? {
+        let Entry::Operation(operation) = entry else {
+            continue;
+        };
+
+        let when = operation.time.with_timezone(&Local).format(LOCAL_TIME_FORMAT);
+        let undone = !applied.iter().any(|applied| applied.number == operation.number);
+        let state = if undone { "undone" } else { "" };
+
+        out.text(format!(
+            "{}{SEPARATOR}{when}{SEPARATOR}{}{SEPARATOR}{}{SEPARATOR}{state}",
+            operation.number, operation.command, format_ids(&operation.task_ids()),
+        ));
+        operations.push(json!({
+            "number": operation.number,
+            "time": format::timestamp(operation.time),
+            "command": operation.command,
+            "tasks": operation.task_ids(),
+            "undone": undone,
+        }));
+    }
+    out.set("operations", Value::Array(operations));
+
+    Ok(())
+}
+
+/// Archives the completed tasks, only those completed on or before `cutoff`
+/// if given. Tasks without a completion time count as old enough.
+fn archive_tasks(out: &mut Output, task_list: &mut TaskList, cutoff: Option<NaiveDate>) -> Result<(), TasksError> {
+    let indexes: Vec<usize> = (0..task_list.tasks.len())
+        .filter(|&index| is_archivable(&task_list.tasks[index], cutoff))
+        .collect();
+
+    if indexes.is_empty() {
+        out.say("No tasks to archive");
+        out.set("archived", json!([]));
+        return Ok(());
+    }
+
+    let archived = task_list.archive(&indexes)?;
+    out.set("archived", output::tasks_json(&archived));
+    out.say(format!("Archived {} tasks: {}", archived.len(), format_ids(&archived.iter().map(Task::id).collect::<Vec<_>>())));
+
+    Ok(())
+}
+
+/// Archives tasks as the database's `archive.after` setting asks, if set.
+fn archive_by_policy(out: &mut Output, task_list: &mut TaskList) -> Result<(), TasksError> {
+    let config = task_list.config()?;
+    let Some(age) = config.get(ARCHIVE_AFTER) else {
+        return Ok(());
+    };
+
+    let cutoff = Some(dates::ago(age, &SystemClock)?);
+
+    if task_list.tasks.iter().any(|task| is_archivable(task, cutoff)) {
+        archive_tasks(out, task_list, cutoff)?;
+    }
+
+    Ok(())
+}
+
+fn is_archivable(task: &Task, cutoff: Option<NaiveDate>) -> bool {
+    let old_enough = match (cutoff, task.completed_at()) {
+        (Some(cutoff), Some(completed_at)) => completed_at.with_timezone(&Local).date_naive() <= cutoff,
+        _ => true,
+    };
+
+    task.is_completed() && old_enough
+}
+
+fn configure(out: &mut Output, task_list: &TaskList, key: Option<&str>, value: Option<&str>) -> Result<(), TasksError> {
+    let mut config = task_list.config()?;
+
+    match (key, value) {
+        (None, _) => {
+            for (key, value) in config.values() {
+                out.text(format!("{key} = {value}"));
+            }
+            out.set("settings", json!(config.values().collect::<BTreeMap<_, _>>()));
+        },
+        (Some(key), None) => {
+            match config.get(key) {
+                Some(value) => out.text(value),
+                None => out.text(format!("{key} is not set")),
+            }
+            out.set("settings", json!({ key: config.get(key) }));
+        },
+        (Some(key), Some("none")) => {
+            config.set(key, None)?;
+            out.say(format!("Removed {key}"));
+        },
+        (Some(key), Some(value)) => {
+            config.set(key, Some(value))?;
+            out.say(format!("Set {key} = {value}"));
+        },
+    }
+
+    Ok(())
+}
+
+/// Gives the tasks without an id, which older files do not store, ids
+/// counting up from `first_id`.
+fn number_tasks(tasks: &mut [Task], first_id: u32) {
+    let unnumbered = tasks.iter_mut().filter(|task| task.id() == 0);
+    for (id, task) in (first_id..).zip(unnumbered) {
+        task.set_id(id);
+    }
+}
+
+fn format_ids(ids: &[u32]) -> String {
+    ids.iter().map(u32::to_string).collect::<Vec<_>>().join(",")
+}
+
+fn migrate_tasks(out: &mut Output, task_list: &mut TaskList, source: &Path) -> Result<(), TasksError> {
+    if !source.exists() {
+        return Err(TasksError::InvalidArgument(format!("No database at {}", source.display())));
+    }
+
+    if !task_list.tasks.is_empty() {
+        return Err(TasksError::InvalidArgument("Target database already has tasks, migrate into an empty one".to_string()));
+    }
+
+    let mut source_store = store::open(source.to_path_buf(), lock::DEFAULT_TIMEOUT)?;
+    source_store.lock(LockMode::Shared)?;
+
+    let mut tasks = source_store.load()?;
+    for warning in source_store.take_warnings() {
+        out.warn(warning);
+    }
+    // Tasks from files older than version 3 have no ids yet.
+    let first_id = task_list.next_free_id()?.max(tasks.iter().map(Task::id).max().unwrap_or(0) + 1);
+    number_tasks(&mut tasks, first_id);
+
+    out.say(format!("Migrating {} tasks from {}", tasks.len(), source.display()));
+
+    task_list.replace(tasks)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tasks 1 to 4, where 1 depends on 2 and 2 on 3.
+    fn chain() -> MemoryStore {
+        let mut tasks: Vec<Task> = (1..=4).map(|id| Task::new(id, format!("Task {id}"))).collect();
+        tasks[0].add_dependency(2);
+        tasks[1].add_dependency(3);
+        MemoryStore::new(tasks)
+    }
+
+    #[test]
+    fn dependency_path_follows_the_chain() {
+        let mut store = chain();
+        let task_list = TaskList::load(&mut store).unwrap();
+
+        assert_eq!(task_list.dependency_path(1, 3), Some(vec![1, 2, 3]));
+        assert_eq!(task_list.dependency_path(3, 1), None);
+        assert_eq!(task_list.dependency_path(1, 4), None);
+    }
+
+    #[test]
+    fn self_dependency_is_a_cycle() {
+        let mut store = chain();
+        let mut task_list = TaskList::load(&mut store).unwrap();
+
+        assert_eq!(task_list.dependency_path(4, 4), Some(vec![4]));
+
+        let result = set_dependencies(&mut Output::new(true), &mut task_list, TaskRef::Id(4), Some(vec![TaskRef::Id(4)]));
+        assert!(matches!(result, Err(TasksError::InvalidArgument(message)) if message.ends_with("cycle 4 -> 4")));
+    }
+
+    #[test]
+    fn closing_a_cycle_names_it() {
+        assert_eq!(format_cycle(3, &[1, 2, 3]), "3 -> 1 -> 2 -> 3");
+
+        let mut store = chain();
+        let mut task_list = TaskList::load(&mut store).unwrap();
+
+        let result = set_dependencies(&mut Output::new(true), &mut task_list, TaskRef::Id(3), Some(vec![TaskRef::Id(1)]));
+        let Err(TasksError::InvalidArgument(message)) = result else {
+            panic!("expected a cycle error, got {result:?}");
+        };
+        assert_eq!(message, "Task 3 cannot depend on task 1, which would create the cycle 3 -> 1 -> 2 -> 3");
+        assert!(store.tasks()[2].dependencies().is_empty());
+    }
+}
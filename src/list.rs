@@ -0,0 +1,429 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
+
+use crate::args::{Arg, Args};
+use crate::config::LIST_COLUMNS;
+use crate::{dates, Clock, Condition, Config, Due, DueStatus, Filter, Task, TasksError};
+
+/// Which tasks `list` shows.
+#[derive(Default)]
+pub struct ListOptions {
+    /// Only pending tasks past their due date.
+    pub overdue: bool,
+    pub due_before: Option<Due>,
+    pub due_after: Option<Due>,
+    /// Given as the words after the flags, e.g. `+bug or priority:H`.
+    pub filter: Option<Filter>,
+    /// Sort keys, most significant first; empty keeps list order.
+    pub sort: Vec<SortKey>,
+    /// Adds columns with the creation time and age of each task.
+    pub age: bool,
+    /// Lists the archive instead of the active tasks.
+    pub archived: bool,
+    /// Only pending tasks that are not blocked, as shown by `next`.
+    pub actionable: bool,
+    /// Shows tasks from every list, even when working in one.
+    pub all_lists: bool,
+    /// The columns to show, in order; empty uses the configured or default
+    /// ones.
+    pub columns: Vec<Column>,
+    /// Wraps long task names onto more lines instead of cutting them short.
+    pub wrap: bool,
+}
+
+impl ListOptions {
+    /// Options for the `next` view: the given filters, limited to tasks
+    /// that can be worked on now, most important first by default.
+    pub(crate) fn build_next(args: &mut Args, clock: &dyn Clock) -> Result<Self, TasksError> {
+        let mut options = Self::build(args, clock)?;
+        options.actionable = true;
+
+        if options.sort.is_empty() {
+            options.sort = [SortField::Priority, SortField::Due, SortField::Created]
+                .into_iter()
+                .map(|field| SortKey { field, descending: false })
+                .collect();
+        }
+
+        Ok(options)
+    }
+
+    pub(crate) fn build(args: &mut Args, clock: &dyn Clock) -> Result<Self, TasksError> {
+        let mut options = ListOptions::default();
+        let mut words = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg {
+                Arg::Flag("--overdue", None) => options.overdue = true,
+                Arg::Flag("--age", None) => options.age = true,
+                Arg::Flag("--archived", None) => options.archived = true,
+                Arg::Flag("--all-lists", None) => options.all_lists = true,
+                Arg::Flag("--wrap", None) => options.wrap = true,
+                Arg::Flag(flag @ "--columns", inline) => options.columns = Column::parse_list(args.value(flag, inline)?)?,
+                Arg::Flag(flag @ "--due-before", inline) => {
+                    options.due_before = Some(dates::parse(args.value(flag, inline)?, clock)?);
+                },
+                Arg::Flag(flag @ "--due-after", inline) => {
+                    options.due_after = Some(dates::parse(args.value(flag, inline)?, clock)?);
+                },
+                Arg::Flag(flag @ "--sort", inline) => {
+                    options.sort = args
+                        .value(flag, inline)?
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()?;
+                },
+                Arg::Flag(flag, _) => return Err(TasksError::UnknownFlag(flag.to_string())),
+                Arg::Value(value) => words.push(value),
+            }
+        }
+
+        if !words.is_empty() {
+            options.filter = Some(Filter::parse(&words.join(" "), clock)?);
+        }
+
+        Ok(options)
+    }
+
+    /// Limits the listing to the named list, unless all lists were asked for.
+    pub(crate) fn within(mut self, list: &str) -> Self {
+        if !self.all_lists {
+            self.filter = Some(match self.filter.take() {
+                Some(filter) => filter.within(list),
+                None => Filter::Condition(Condition::List(list.to_string())),
+            });
+        }
+        self
+    }
+
+    /// The columns to show: those given with `--columns`, else the
+    /// `list.columns` setting, else the defaults. `--age` adds the creation
+    /// time and age before the task name when they are not already shown.
+    pub(crate) fn columns(&self, config: &Config) -> Result<Vec<Column>, TasksError> {
+        let mut columns = match (self.columns.is_empty(), config.get(LIST_COLUMNS)) {
+            (false, _) => self.columns.clone(),
+            (true, Some(configured)) => Column::parse_list(configured)?,
+            (true, None) => Column::DEFAULT.to_vec(),
+        };
+
+        if self.age {
+            let position = columns.iter().position(|&column| column == Column::Task).unwrap_or(columns.len());
+            let missing: Vec<Column> =
+                [Column::Created, Column::Age].into_iter().filter(|column| !columns.contains(column)).collect();
+            columns.splice(position..position, missing);
+        }
+
+        Ok(columns)
+    }
+
+    pub(crate) fn matches(&self, task: &Task, now: NaiveDateTime) -> bool {
+        let due = task.due();
+
+        if self.overdue && (task.is_completed() || due.is_none_or(|due| due.status(now) != DueStatus::Overdue)) {
+            return false;
+        }
+
+        if self.filter.as_ref().is_some_and(|filter| !filter.matches(task)) {
+            return false;
+        }
+
+        if let Some(bound) = &self.due_before {
+            if !due.is_some_and(|due| due.is_before(bound)) {
+                return false;
+            }
+        }
+
+        if let Some(bound) = &self.due_after {
+            if !due.is_some_and(|due| due.is_after(bound)) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Orders `tasks` by the sort keys, breaking ties by id.
+    pub(crate) fn sort(&self, tasks: &mut [&Task]) {
+        if self.sort.is_empty() {
+            return;
+        }
+
+        tasks.sort_by(|a, b| {
+            self.sort
+                .iter()
+                .map(|key| key.compare(a, b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or_else(|| a.id().cmp(&b.id()))
+        });
+    }
+}
+
+/// A column of the `list` table.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    Id,
+    /// `[x]` for completed tasks, `[ ]` for pending ones.
+    Status,
+    Priority,
+    /// The due date, marked when it is today or past.
+    Due,
+    Project,
+    Tags,
+    List,
+    /// When the task was created, in local time.
+    Created,
+    /// How long ago the task was created, or when it was completed.
+    Age,
+    /// The task name, indented under its parent, with its progress,
+    /// recurrence and blockers. Long names are cut short or wrapped to fit
+    /// the terminal.
+    Task,
+}
+
+impl Column {
+    /// The columns shown unless others are asked for.
+    pub const DEFAULT: &[Column] =
+        &[Column::Id, Column::Status, Column::Priority, Column::Due, Column::Project, Column::Tags, Column::Task];
+
+    pub fn heading(self) -> &'static str {
+        match self {
+            Column::Id => "ID",
+            Column::Status => "Done",
+            Column::Priority => "P",
+            Column::Due => "Due",
+            Column::Project => "Project",
+            Column::Tags => "Tags",
+            Column::List => "List",
+            Column::Created => "Created",
+            Column::Age => "Age",
+            Column::Task => "Task",
+        }
+    }
+
+    /// Parses a comma-separated list of column names, e.g. `id,due,task`.
+    pub fn parse_list(value: &str) -> Result<Vec<Column>, TasksError> {
+        let columns: Vec<Column> = value.split(',').map(str::parse).collect::<Result<_, _>>()?;
+
+        for (index, column) in columns.iter().enumerate() {
+            if columns[..index].contains(column) {
+                return Err(TasksError::InvalidArgument(format!("Column {:?} is given twice", column.heading())));
+            }
+        }
+
+        Ok(columns)
+    }
+}
+
+impl FromStr for Column {
+    type Err = TasksError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let column = match value.trim().to_lowercase().as_str() {
+            "id" => Column::Id,
+            "status" => Column::Status,
+            "priority" => Column::Priority,
+            "due" => Column::Due,
+            "project" => Column::Project,
+            "tags" => Column::Tags,
+            "list" => Column::List,
+            "created" => Column::Created,
+            "age" => Column::Age,
+            "task" => Column::Task,
+            _ => {
+                return Err(TasksError::InvalidArgument(format!(
+                    "Invalid column {value:?}, expected id, status, priority, due, project, tags, list, created, age or task",
+                )));
+            },
+        };
+
+        Ok(column)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortField {
+    /// Highest priority first, tasks without one last.
+    Priority,
+    /// Earliest due first, tasks without a due date last.
+    Due,
+    /// Oldest first, tasks without a creation time last.
+    Created,
+    /// Alphabetical, ignoring case.
+    Name,
+}
+
+/// A field to sort by; `-` in front of the name reverses it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl SortKey {
+    /// Compares two tasks by this key. Tasks missing the field sort last
+    /// in either direction.
+    fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        let descending = self.descending;
+        match self.field {
+            SortField::Priority => {
+                compare_missing_last(a.priority().map(Reverse), b.priority().map(Reverse), descending)
+            },
+            SortField::Due => compare_missing_last(a.due(), b.due(), descending),
+            SortField::Created => compare_missing_last(a.created(), b.created(), descending),
+            SortField::Name => {
+                let ordering = a.name().to_lowercase().cmp(&b.name().to_lowercase());
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            },
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = TasksError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (descending, name) = match value.strip_prefix('-') {
+            Some(name) => (true, name),
+            None => (false, value),
+        };
+
+        let field = match name.trim().to_lowercase().as_str() {
+            "priority" => SortField::Priority,
+            "due" => SortField::Due,
+            "created" => SortField::Created,
+            "name" => SortField::Name,
+            _ => {
+                return Err(TasksError::InvalidArgument(format!(
+                    "Invalid sort key {value:?}, expected priority, due, created or name",
+                )));
+            },
+        };
+
+        Ok(Self { field, descending })
+    }
+}
+
+/// Compares two optional values, putting missing ones last whether or not
+/// the present ones are in `descending` order.
+fn compare_missing_last<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Orders `tasks` as a tree, each subtask after its parent and indented one
+/// level deeper, keeping the given order among siblings. Tasks whose parent
+/// is not among `tasks` are shown at the top level.
+pub(crate) fn tree<'a>(tasks: &[&'a Task]) -> Vec<(&'a Task, usize)> {
+    let shown: HashSet<u32> = tasks.iter().map(|task| task.id()).collect();
+    let mut children: HashMap<u32, Vec<&Task>> = HashMap::new();
+    let mut roots = Vec::new();
+
+    for &task in tasks {
+        match task.parent().filter(|parent| shown.contains(parent)) {
+            Some(parent) => children.entry(parent).or_default().push(task),
+            None => roots.push(task),
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(tasks.len());
+    let mut stack: Vec<(&Task, usize)> = roots.into_iter().rev().map(|task| (task, 0)).collect();
+    let mut visited = HashSet::new();
+
+    while let Some((task, depth)) = stack.pop() {
+        if !visited.insert(task.id()) {
+            continue;
+        }
+        ordered.push((task, depth));
+
+        for &child in children.get(&task.id()).into_iter().flatten().rev() {
+            stack.push((child, depth + 1));
+        }
+    }
+
+    // Tasks caught in a parent cycle are unreachable from any root.
+    ordered.extend(tasks.iter().filter(|task| !visited.contains(&task.id())).map(|&task| (task, 0)));
+
+    ordered
+}
+
+/// How long ago `created` was, e.g. `3 days`.
+pub(crate) fn format_age(created: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let age = now - created;
+
+    match (age.num_days(), age.num_hours()) {
+        (0, 0) => "under an hour".to_string(),
+        (0, hours) => plural(hours, "hour"),
+        (days, _) => plural(days, "day"),
+    }
+}
+
+/// When a task was completed, counted in local calendar days from `today`.
+pub(crate) fn format_completed(completed_at: DateTime<Utc>, today: NaiveDate) -> String {
+    match (today - completed_at.with_timezone(&Local).date_naive()).num_days() {
+        days if days <= 0 => "completed today".to_string(),
+        1 => "completed yesterday".to_string(),
+        days => format!("completed {days} days ago"),
+    }
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn task(id: u32, created: Option<(u32, u32)>) -> Task {
+        let mut task = Task::new(id, format!("task {id}"));
+        if let Some((day, hour)) = created {
+            task.stamp_created(Utc.with_ymd_and_hms(2026, 10, day, hour, 0, 0).unwrap());
+        }
+        task
+    }
+
+    fn sorted_ids(keys: &str, tasks: &[Task]) -> Vec<u32> {
+        let options = ListOptions {
+            sort: keys.split(',').map(|key| key.parse().unwrap()).collect(),
+            ..ListOptions::default()
+        };
+        let mut tasks: Vec<&Task> = tasks.iter().collect();
+        options.sort(&mut tasks);
+        tasks.iter().map(|task| task.id()).collect()
+    }
+
+    #[test]
+    fn created_sorts_by_timestamp_with_missing_last() {
+        // Task 1 was migrated from a file without timestamps; task 4 was
+        // created before task 2 despite its higher id.
+        let tasks = [task(1, None), task(2, Some((14, 9))), task(3, Some((16, 8))), task(4, Some((12, 17)))];
+
+        assert_eq!(sorted_ids("created", &tasks), [4, 2, 3, 1]);
+        assert_eq!(sorted_ids("-created", &tasks), [3, 2, 4, 1]);
+    }
+
+    #[test]
+    fn ties_are_broken_by_id() {
+        let tasks = [task(3, Some((14, 9))), task(1, None), task(2, Some((14, 9))), task(4, None)];
+
+        assert_eq!(sorted_ids("created", &tasks), [2, 3, 1, 4]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::env;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::TasksError;
+
+const DB_ENV: &str = "TASKS_DB";
+const PROJECT_FILENAME: &str = ".tasks";
+const DATA_DIRNAME: &str = "tasks";
+const DATA_FILENAME: &str = "tasks.txt";
+
+/// Where the database path came from, in order of precedence.
+pub enum DbSource {
+    Flag,
+    Env,
+    Project,
+    XdgDataHome,
+    Home,
+}
+
+pub struct DbLocation {
+    pub path: PathBuf,
+    pub source: DbSource,
+}
+
+impl DbLocation {
+    /// Resolves the database path: `--db` flag, then `TASKS_DB`, then the
+    /// nearest `.tasks` file above the current directory, then the XDG data
+    /// directory.
+    pub fn resolve(flag: Option<&Path>) -> Result<Self, TasksError> {
+        if let Some(path) = flag {
+            return Ok(Self::new(path.to_path_buf(), DbSource::Flag));
+        }
+
+        if let Some(path) = env::var_os(DB_ENV).filter(|path| !path.is_empty()) {
+            return Ok(Self::new(PathBuf::from(path), DbSource::Env));
+        }
+
+        let current_dir = env::current_dir()?;
+        if let Some(path) = find_project_file(&current_dir) {
+            return Ok(Self::new(path, DbSource::Project));
+        }
+
+        // The XDG spec asks for relative values to be ignored.
+        let xdg_data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute());
+        if let Some(data_home) = xdg_data_home {
+            return Ok(Self::new(data_path(&data_home), DbSource::XdgDataHome));
+        }
+
+        let home = env::var_os("HOME")
+            .filter(|home| !home.is_empty())
+            .ok_or_else(|| io::Error::new(
+                io::ErrorKind::NotFound,
+                "Cannot determine data directory: neither XDG_DATA_HOME nor HOME is set",
+            ))?;
+        let data_home = Path::new(&home).join(".local").join("share");
+
+        Ok(Self::new(data_path(&data_home), DbSource::Home))
+    }
+
+    fn new(path: PathBuf, source: DbSource) -> Self {
+        Self { path, source }
+    }
+}
+
+impl fmt::Display for DbSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbSource::Flag => write!(f, "--db flag"),
+            DbSource::Env => write!(f, "{DB_ENV} environment variable"),
+            DbSource::Project => write!(f, "{PROJECT_FILENAME} file found in a parent directory"),
+            DbSource::XdgDataHome => write!(f, "$XDG_DATA_HOME"),
+            DbSource::Home => write!(f, "default data directory (XDG_DATA_HOME unset, using $HOME/.local/share)"),
+        }
+    }
+}
+
+fn find_project_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_FILENAME))
+        .find(|candidate| candidate.is_file())
+}
+
+fn data_path(data_home: &Path) -> PathBuf {
+    data_home.join(DATA_DIRNAME).join(DATA_FILENAME)
+}
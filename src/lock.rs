@@ -0,0 +1,109 @@
+use std::env;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::TasksError;
+
+const LOCK_SUFFIX: &str = ".lock";
+const TIMEOUT_ENV: &str = "TASKS_LOCK_TIMEOUT";
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+/// Advisory lock on a `.lock` file next to the database, released on drop.
+///
+/// Exclusive holders write their PID into the lock file so that a process
+/// timing out can report who is holding the database.
+pub struct DbLock {
+    file: File,
+    mode: LockMode,
+}
+
+impl DbLock {
+    pub fn acquire(db_path: &Path, mode: LockMode, timeout: Duration) -> Result<Self, TasksError> {
+        let lock_path = crate::sibling_path(db_path, LOCK_SUFFIX);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&lock_path)?;
+
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let attempt = match mode {
+                LockMode::Shared => file.try_lock_shared(),
+                LockMode::Exclusive => file.try_lock(),
+            };
+
+            match attempt {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => thread::sleep(RETRY_INTERVAL),
+                Err(TryLockError::WouldBlock) => {
+                    return Err(TasksError::Locked {
+                        holder: read_holder(&mut file),
+                        path: lock_path,
+                        timeout,
+                    });
+                },
+                Err(TryLockError::Error(err)) => return Err(err.into()),
+            }
+        }
+
+        if mode == LockMode::Exclusive {
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            write!(file, "{}", process::id())?;
+            file.flush()?;
+        }
+
+        Ok(Self { file, mode })
+    }
+}
+
+impl Drop for DbLock {
+    fn drop(&mut self) {
+        if self.mode == LockMode::Exclusive {
+            let _ = self.file.set_len(0);
+        }
+        let _ = self.file.unlock();
+    }
+}
+
+/// Resolves the lock wait timeout from the flag value, then `TASKS_LOCK_TIMEOUT`.
+pub fn lock_timeout(flag: Option<Duration>) -> Result<Duration, TasksError> {
+    if let Some(timeout) = flag {
+        return Ok(timeout);
+    }
+
+    match env::var(TIMEOUT_ENV) {
+        Ok(value) => parse_timeout(&value),
+        Err(_) => Ok(DEFAULT_TIMEOUT),
+    }
+}
+
+/// Parses a timeout given in (possibly fractional) seconds.
+pub fn parse_timeout(value: &str) -> Result<Duration, TasksError> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| TasksError::InvalidArgument(format!("Invalid lock timeout {value:?}, expected seconds")))
+}
+
+fn read_holder(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
@@ -1,17 +1,52 @@
 use std::{env, process};
 
-use todos::Command;
+use todos::{Command, Options, TasksError};
+
+/// Exit codes, one per category of failure:
+///
+/// - 0: success
+/// - 2: usage error (unknown command or flag, missing or invalid argument
+///   or setting, command not supported by this build)
+/// - 3: the referenced task does not exist
+/// - 4: the database is corrupt
+/// - 5: reading or writing the database failed
+/// - 6: timed out waiting for another process to release the database
+fn exit_code(err: &TasksError) -> i32 {
+    match err {
+        TasksError::UnknownCommand(_)
+        | TasksError::UnknownFlag(_)
+        | TasksError::MissingArgument(_)
+        | TasksError::InvalidArgument(_)
+        | TasksError::InvalidFilter { .. }
+        | TasksError::Unsupported(_) => 2,
+        TasksError::TaskNotFound(_) => 3,
+        TasksError::Parse { .. } => 4,
+        TasksError::Io(_) | TasksError::Storage(_) => 5,
+        TasksError::Locked { .. } => 6,
+    }
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    let command = Command::build(&args).unwrap_or_else(|err| {
-        eprintln!("Command error: {err}");
-        process::exit(1);
-    });
-    if let Err(err) = todos::run(command) {
-        eprintln!("App error: {err}");
-        process::exit(1);
+    let json = Options::json_requested(&args);
+
+    let (options, args) = Options::build(&args).unwrap_or_else(|err| fail("Command error", &err, json));
+    let command = Command::build(args).unwrap_or_else(|err| fail("Command error", &err, json));
+    if let Err(err) = todos::run_cli(&options, command) {
+        fail("App error", &err, json);
     }
 
     process::exit(0);
 }
+
+/// Reports `err` on standard error, or as a JSON document on standard
+/// output, and exits with its code.
+fn fail(context: &str, err: &TasksError, json: bool) -> ! {
+    let code = exit_code(err);
+    if json {
+        println!("{}", todos::error_document(err, code));
+    } else {
+        eprintln!("{context}: {err}");
+    }
+    process::exit(code);
+}
@@ -0,0 +1,135 @@
+//! Where commands send what they report: printed as text as they go, or
+//! with `--json`, gathered into a single JSON document printed at the end.
+//!
+//! Every document is an object with `version` (bumped only for changes
+//! that break existing readers), `ok` and, on success, `command`,
+//! `messages` and `warnings`, plus fields that depend on the command.
+//! Failures carry an `error` object instead.
+
+use std::io::{self, IsTerminal};
+
+use serde_json::{json, Map, Value};
+
+use crate::{format, Task, TasksError};
+
+/// The version of the JSON document layout.
+pub const JSON_VERSION: u32 = 1;
+
+pub struct Output {
+    json: bool,
+    messages: Vec<String>,
+    warnings: Vec<String>,
+    fields: Map<String, Value>,
+}
+
+impl Output {
+    pub fn new(json: bool) -> Self {
+        Self {
+            json,
+            messages: Vec::new(),
+            warnings: Vec::new(),
+            fields: Map::new(),
+        }
+    }
+
+    /// Whether questions can be asked on the terminal, which JSON output
+    /// rules out.
+    pub fn can_ask(&self) -> bool {
+        !self.json && io::stdin().is_terminal()
+    }
+
+    /// Reports what happened: printed as a line of text, or kept in the
+    /// document's `messages`.
+    pub fn say(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        if self.json {
+            self.messages.push(message);
+        } else {
+            println!("{message}");
+        }
+    }
+
+    /// Prints a line of text output, such as a table row. The document
+    /// carries the same data in its fields instead.
+    pub fn text(&mut self, line: impl AsRef<str>) {
+        if !self.json {
+            println!("{}", line.as_ref());
+        }
+    }
+
+    /// Prints a warning on standard error, or keeps it in the document's
+    /// `warnings`.
+    pub fn warn(&mut self, warning: impl Into<String>) {
+        let warning = warning.into();
+        if self.json {
+            self.warnings.push(warning);
+        } else {
+            eprintln!("Warning: {warning}");
+        }
+    }
+
+    /// Sets a field of the document; text output ignores it.
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.fields.insert(key.to_string(), value);
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        self.fields.contains_key(key)
+    }
+
+    /// Prints the document for a successful `command`, if there is one.
+    pub fn finish(self, command: &str) {
+        if !self.json {
+            return;
+        }
+
+        let mut document = Map::new();
+        document.insert("version".to_string(), json!(JSON_VERSION));
+        document.insert("ok".to_string(), json!(true));
+        document.insert("command".to_string(), json!(command));
+        document.extend(self.fields);
+        document.insert("messages".to_string(), json!(self.messages));
+        document.insert("warnings".to_string(), json!(self.warnings));
+
+        println!("{}", Value::Object(document));
+    }
+}
+
+/// The document describing a failure, with the process exit code it leads to.
+pub fn error_document(err: &TasksError, exit_code: i32) -> String {
+    let mut error = Map::new();
+    error.insert("kind".to_string(), json!(err.kind()));
+    error.insert("message".to_string(), json!(err.to_string()));
+    error.insert("exit_code".to_string(), json!(exit_code));
+    if let TasksError::InvalidFilter { expression, position, .. } = err {
+        error.insert("expression".to_string(), json!(expression));
+        error.insert("position".to_string(), json!(position));
+    }
+
+    json!({ "version": JSON_VERSION, "ok": false, "error": error }).to_string()
+}
+
+/// A task as it appears in documents. Every key is always present, with
+/// `null` or an empty list when the task has no such attribute.
+pub fn task_json(task: &Task) -> Value {
+    json!({
+        "id": task.id(),
+        "name": task.name(),
+        "completed": task.is_completed(),
+        "due": task.due().map(|due| due.to_string()),
+        "priority": task.priority().map(|priority| priority.to_string()),
+        "tags": task.tags(),
+        "project": task.project(),
+        "list": task.list(),
+        "parent": task.parent(),
+        "depends": task.dependencies(),
+        "recurrence": task.recurrence().map(|recurrence| recurrence.to_string()),
+        "created": task.created().map(format::timestamp),
+        "modified": task.modified().map(format::timestamp),
+        "completed_at": task.completed_at().map(format::timestamp),
+    })
+}
+
+pub fn tasks_json<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> Value {
+    Value::Array(tasks.into_iter().map(task_json).collect())
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::TasksError;
+
+/// How important a task is. Orders from `Low` to `High`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+/// Parses `H`, `M` or `L`, or the full names, in any case.
+impl FromStr for Priority {
+    type Err = TasksError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "h" | "high" => Ok(Priority::High),
+            "m" | "medium" => Ok(Priority::Medium),
+            "l" | "low" => Ok(Priority::Low),
+            _ => Err(TasksError::InvalidArgument(format!("Invalid priority {value:?}, expected H, M or L"))),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let letter = match self {
+            Priority::High => "H",
+            Priority::Medium => "M",
+            Priority::Low => "L",
+        };
+
+        write!(f, "{letter}")
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+use crate::TasksError;
+
+const ACCEPTED_FORMS: &str =
+    "daily, weekly:<weekdays> (weekly:mon,thu), monthly:<day> (monthly:15), monthly:last-business-day, after:<N>d";
+
+/// When a recurring task comes back after it is completed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Recurrence {
+    /// Every day.
+    Daily,
+    /// On the given weekdays, kept in Monday-first order.
+    Weekly(Vec<Weekday>),
+    /// On the given day of every month, or the month's last day if it is
+    /// shorter.
+    MonthlyOnDay(u32),
+    /// On the last Monday to Friday of every month.
+    MonthlyLastBusinessDay,
+    /// The given number of days after the previous occurrence was completed.
+    AfterCompletion(u32),
+}
+
+impl Recurrence {
+    /// Whether occurrences follow a calendar rather than the completion date.
+    pub fn is_scheduled(&self) -> bool {
+        !matches!(self, Recurrence::AfterCompletion(_))
+    }
+
+    /// The first occurrence on or after `date`; `date` itself for rules
+    /// that follow the completion date.
+    pub fn first(&self, date: NaiveDate) -> Result<NaiveDate, TasksError> {
+        match date.pred_opt() {
+            Some(previous) if self.is_scheduled() => self.next(previous, date),
+            _ => Ok(date),
+        }
+    }
+
+    /// The occurrence following one due on `due` and completed on
+    /// `completed`. Scheduled rules move strictly past `due`. Fails when
+    /// that is past the last date chrono can represent.
+    pub fn next(&self, due: NaiveDate, completed: NaiveDate) -> Result<NaiveDate, TasksError> {
+        let next = match self {
+            Recurrence::Daily => due.checked_add_days(Days::new(1)),
+            Recurrence::Weekly(weekdays) => (1..=7)
+                .map_while(|days| due.checked_add_days(Days::new(days)))
+                .find(|date| weekdays.contains(&date.weekday())),
+            Recurrence::MonthlyOnDay(day) => {
+                let this_month = day_in_month(due, *day);
+                if this_month > due {
+                    Some(this_month)
+                } else {
+                    first_of_next_month(due).map(|month| day_in_month(month, *day))
+                }
+            },
+            Recurrence::MonthlyLastBusinessDay => {
+                let this_month = last_business_day(due);
+                if this_month > due {
+                    Some(this_month)
+                } else {
+                    first_of_next_month(due).map(last_business_day)
+                }
+            },
+            Recurrence::AfterCompletion(days) => completed.checked_add_days(Days::new(u64::from(*days))),
+        };
+
+        next.ok_or_else(|| TasksError::InvalidArgument(format!("The occurrence after {due} repeating {self} is out of range")))
+    }
+}
+
+/// `day` in the month of `date`, clamped to the month's length.
+fn day_in_month(date: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day).rev().find_map(|day| date.with_day(day)).unwrap_or(date)
+}
+
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    day_in_month(date, 1).checked_add_months(Months::new(1))
+}
+
+fn last_business_day(date: NaiveDate) -> NaiveDate {
+    let mut last = day_in_month(date, 31);
+    while matches!(last.weekday(), Weekday::Sat | Weekday::Sun) {
+        last = last - Days::new(1);
+    }
+    last
+}
+
+/// Parses the forms listed in `ACCEPTED_FORMS`, in any case.
+impl FromStr for Recurrence {
+    type Err = TasksError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || TasksError::InvalidArgument(format!("Invalid recurrence {value:?}, accepted forms: {ACCEPTED_FORMS}"));
+
+        let normalized = value.trim().to_lowercase();
+        let (kind, argument) = match normalized.split_once(':') {
+            Some((kind, argument)) => (kind, Some(argument)),
+            None => (normalized.as_str(), None),
+        };
+
+        let recurrence = match (kind, argument) {
+            ("daily", None) => Recurrence::Daily,
+            ("weekly", Some(weekdays)) => {
+                let mut weekdays: Vec<Weekday> =
+                    weekdays.split(',').map(|weekday| weekday.parse().map_err(|_| invalid())).collect::<Result<_, _>>()?;
+                weekdays.sort_by_key(Weekday::num_days_from_monday);
+                weekdays.dedup();
+                Recurrence::Weekly(weekdays)
+            },
+            ("monthly", Some("last-business-day")) => Recurrence::MonthlyLastBusinessDay,
+            ("monthly", Some(day)) => match day.parse() {
+                Ok(day @ 1..=31) => Recurrence::MonthlyOnDay(day),
+                _ => return Err(invalid()),
+            },
+            ("after", Some(days)) => match days.strip_suffix('d').unwrap_or(days).parse() {
+                Ok(days) if days > 0 => Recurrence::AfterCompletion(days),
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        };
+
+        Ok(recurrence)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekly(weekdays) => {
+                let names: Vec<String> = weekdays.iter().map(|weekday| weekday.to_string().to_lowercase()).collect();
+                write!(f, "weekly:{}", names.join(","))
+            },
+            Recurrence::MonthlyOnDay(day) => write!(f, "monthly:{day}"),
+            Recurrence::MonthlyLastBusinessDay => write!(f, "monthly:last-business-day"),
+            Recurrence::AfterCompletion(days) => write!(f, "after:{days}d"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn next(rule: &str, due: NaiveDate) -> NaiveDate {
+        rule.parse::<Recurrence>().unwrap().next(due, date(2026, 10, 18)).unwrap()
+    }
+
+    #[test]
+    fn daily_and_weekly() {
+        assert_eq!(next("daily", date(2026, 12, 31)), date(2027, 1, 1));
+        // 2026-10-15 is a Thursday.
+        assert_eq!(next("weekly:mon,thu", date(2026, 10, 15)), date(2026, 10, 19));
+        assert_eq!(next("weekly:mon,thu", date(2026, 10, 19)), date(2026, 10, 22));
+        assert_eq!(next("weekly:thu", date(2026, 10, 15)), date(2026, 10, 22));
+    }
+
+    #[test]
+    fn monthly_on_day_clamps_to_month_end() {
+        assert_eq!(next("monthly:15", date(2026, 10, 15)), date(2026, 11, 15));
+        assert_eq!(next("monthly:15", date(2026, 10, 3)), date(2026, 10, 15));
+        assert_eq!(next("monthly:31", date(2026, 1, 31)), date(2026, 2, 28));
+        assert_eq!(next("monthly:31", date(2028, 1, 31)), date(2028, 2, 29));
+        // After a clamped month, the rule goes back to its own day.
+        assert_eq!(next("monthly:31", date(2026, 2, 28)), date(2026, 3, 31));
+        assert_eq!(next("monthly:31", date(2026, 12, 31)), date(2027, 1, 31));
+    }
+
+    #[test]
+    fn last_business_day_skips_weekends() {
+        // October 2026 ends on a Saturday, January 2027 on a Sunday.
+        assert_eq!(next("monthly:last-business-day", date(2026, 10, 1)), date(2026, 10, 30));
+        assert_eq!(next("monthly:last-business-day", date(2026, 10, 30)), date(2026, 11, 30));
+        assert_eq!(next("monthly:last-business-day", date(2026, 12, 31)), date(2027, 1, 29));
+    }
+
+    #[test]
+    fn after_completion_counts_from_completion() {
+        assert_eq!(next("after:3d", date(2026, 10, 1)), date(2026, 10, 21));
+    }
+
+    #[test]
+    fn first_occurrence() {
+        let rule: Recurrence = "weekly:mon".parse().unwrap();
+        assert_eq!(rule.first(date(2026, 10, 19)).unwrap(), date(2026, 10, 19));
+        assert_eq!(rule.first(date(2026, 10, 20)).unwrap(), date(2026, 10, 26));
+        assert_eq!("after:2d".parse::<Recurrence>().unwrap().first(date(2026, 10, 20)).unwrap(), date(2026, 10, 20));
+    }
+
+    #[test]
+    fn occurrences_past_the_last_date_fail() {
+        let today = date(2026, 10, 18);
+        assert!("after:99999999d".parse::<Recurrence>().unwrap().next(today, today).is_err());
+        assert!("daily".parse::<Recurrence>().unwrap().next(NaiveDate::MAX, today).is_err());
+        assert!("monthly:15".parse::<Recurrence>().unwrap().next(NaiveDate::MAX, today).is_err());
+    }
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for rule in ["daily", "weekly:mon,thu", "monthly:15", "monthly:last-business-day", "after:3d"] {
+            assert_eq!(rule.parse::<Recurrence>().unwrap().to_string(), rule);
+        }
+        assert_eq!("Weekly:THU,mon,thu".parse::<Recurrence>().unwrap().to_string(), "weekly:mon,thu");
+        for rule in ["hourly", "monthly:0", "monthly:32", "after:0d", "weekly:", "weekly:xyz"] {
+            assert!(rule.parse::<Recurrence>().is_err(), "{rule} was accepted");
+        }
+    }
+}
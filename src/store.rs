@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{FileStore, LockMode, Task, TasksError};
+
+pub mod file;
+pub mod memory;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+const SQLITE_EXTENSIONS: &[&str] = &["sqlite", "sqlite3"];
+
+/// Persistence backend for a task list.
+///
+/// Stores only have to implement `load` and `save`. The incremental methods
+/// are called after a single task changed, with the whole updated list, and
+/// default to a full `save`; backends that can write one record at a time
+/// should override them.
+pub trait TaskStore {
+    /// Loads every task, in list order.
+    fn load(&mut self) -> Result<Vec<Task>, TasksError>;
+
+    /// Replaces the stored tasks with `tasks`.
+    fn save(&mut self, tasks: &[Task]) -> Result<(), TasksError>;
+
+    /// Records that `tasks[index]` was added.
+    fn insert(&mut self, tasks: &[Task], _index: usize) -> Result<(), TasksError> {
+        self.save(tasks)
+    }
+
+    /// Records that `tasks[index]` was modified.
+    fn update(&mut self, tasks: &[Task], _index: usize) -> Result<(), TasksError> {
+        self.save(tasks)
+    }
+
+    /// Records that `removed` was deleted, leaving `tasks`.
+    fn delete(&mut self, tasks: &[Task], _removed: &Task) -> Result<(), TasksError> {
+        self.save(tasks)
+    }
+
+    /// The lowest id never given to a task in this store. It only grows, so
+    /// the ids of deleted tasks are not handed out again. Stores that keep
+    /// no such mark return 1.
+    fn next_id(&self) -> Result<u32, TasksError> {
+        Ok(1)
+    }
+
+    /// Returns and forgets notices about the last `load`, such as a
+    /// recovery from a backup, for the caller to show as warnings.
+    fn take_warnings(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The path of a file kept next to the database, such as the undo
+    /// journal, named by appending `suffix`. Stores without a place on disk
+    /// have none, which disables the features built on them.
+    fn sidecar_path(&self, _suffix: &str) -> Option<PathBuf> {
+        None
+    }
+
+    /// Guards the store against concurrent writers until it is dropped.
+    /// Stores that are not shared between processes need not lock.
+    fn lock(&mut self, _mode: LockMode) -> Result<(), TasksError> {
+        Ok(())
+    }
+}
+
+/// The id after the highest one in `tasks`.
+pub(crate) fn id_after(tasks: &[Task]) -> u32 {
+    tasks.iter().map(Task::id).max().unwrap_or(0) + 1
+}
+
+/// Opens the database at `path`, choosing the backend from its extension:
+/// `.sqlite` and `.sqlite3` files use SQLite, anything else the text format.
+pub fn open(path: PathBuf, lock_timeout: Duration) -> Result<Box<dyn TaskStore>, TasksError> {
+    if !is_sqlite_path(&path) {
+        return Ok(Box::new(FileStore::new(path).with_lock_timeout(lock_timeout)));
+    }
+
+    #[cfg(feature = "sqlite")]
+    return Ok(Box::new(sqlite::SqliteStore::open(path)?.with_lock_timeout(lock_timeout)?));
+
+    #[cfg(not(feature = "sqlite"))]
+    Err(TasksError::Unsupported(format!(
+        "{} is a SQLite database, but this build lacks the sqlite feature",
+        path.display(),
+    )))
+}
+
+fn is_sqlite_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| SQLITE_EXTENSIONS.contains(&extension))
+}
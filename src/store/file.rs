@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::format;
+use crate::lock::{self, DbLock};
+use crate::store::id_after;
+use crate::{sibling_path, LockMode, Task, TaskStore, TasksError};
+
+const BACKUP_SUFFIX: &str = ".bak";
+const TMP_SUFFIX: &str = ".tmp";
+
+/// The pipe-delimited text database.
+///
+/// Saves go through a synced temporary file renamed over the database, with
+/// the previous version kept next to it as a `.bak` backup.
+pub struct FileStore {
+    path: PathBuf,
+    lock_timeout: Duration,
+    lock: Option<DbLock>,
+    /// The id mark read by `load` or written by `save`, if either ran.
+    next_id: Option<u32>,
+    warnings: Vec<String>,
+}
+
+impl FileStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock_timeout: lock::DEFAULT_TIMEOUT,
+            lock: None,
+            next_id: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Sets how long `lock` waits for other processes to release the database.
+    pub fn with_lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn create_parent_dir(&self) -> io::Result<()> {
+        match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl TaskStore for FileStore {
+    /// Loads the database, falling back to the backup left by the last save
+    /// when the main file is missing or cannot be parsed. A recovery is
+    /// reported through `take_warnings`.
+    fn load(&mut self) -> Result<Vec<Task>, TasksError> {
+        self.warnings.clear();
+        let path = &self.path;
+        let backup_path = sibling_path(path, BACKUP_SUFFIX);
+
+        let (tasks, next_id) = match read_tasks(path) {
+            Ok(Some(contents)) => contents,
+            Ok(None) => match read_tasks(&backup_path)? {
+                Some(contents) => {
+                    let warning = format!("Database {} is missing, recovered from {}", path.display(), backup_path.display());
+                    self.warnings.push(warning);
+                    contents
+                },
+                None => (Vec::new(), 1),
+            },
+            Err(err) => match read_tasks(&backup_path) {
+                Ok(Some(contents)) => {
+                    let warning =
+                        format!("Database {} is unreadable ({err}), recovered from {}", path.display(), backup_path.display());
+                    self.warnings.push(warning);
+                    contents
+                },
+                _ => return Err(err),
+            },
+        };
+
+        self.next_id = Some(next_id);
+        Ok(tasks)
+    }
+
+    fn save(&mut self, tasks: &[Task]) -> Result<(), TasksError> {
+        self.create_parent_dir()?;
+
+        // A store that was not loaded keeps the mark already in the file.
+        let next_id = match self.next_id {
+            Some(next_id) => next_id,
+            None => read_tasks(&self.path).ok().flatten().map_or(1, |(_, next_id)| next_id),
+        };
+        let next_id = next_id.max(id_after(tasks));
+
+        let tmp_path = sibling_path(&self.path, TMP_SUFFIX);
+        let backup_path = sibling_path(&self.path, BACKUP_SUFFIX);
+
+        let tmp_file = File::create(&tmp_path)?;
+        let mut writer = io::BufWriter::new(&tmp_file);
+
+        writeln!(writer, "{}", format::header(next_id))?;
+        for task in tasks {
+            writeln!(writer, "{}", task)?;
+        }
+
+        writer.flush()?;
+        drop(writer);
+        tmp_file.sync_all()?;
+
+        if self.path.exists() {
+            match fs::remove_file(&backup_path) {
+                Ok(()) => {},
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {},
+                Err(err) => return Err(err.into()),
+            }
+            if fs::hard_link(&self.path, &backup_path).is_err() {
+                fs::copy(&self.path, &backup_path)?;
+            }
+        }
+
+        fs::rename(&tmp_path, &self.path)?;
+        sync_parent_dir(&self.path)?;
+        self.next_id = Some(next_id);
+
+        Ok(())
+    }
+
+    fn next_id(&self) -> Result<u32, TasksError> {
+        Ok(self.next_id.unwrap_or(1))
+    }
+
+    fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn sidecar_path(&self, suffix: &str) -> Option<PathBuf> {
+        Some(sibling_path(&self.path, suffix))
+    }
+
+    fn lock(&mut self, mode: LockMode) -> Result<(), TasksError> {
+        self.create_parent_dir()?;
+        self.lock = Some(DbLock::acquire(&self.path, mode, self.lock_timeout)?);
+
+        Ok(())
+    }
+}
+
+/// Reads and parses a database file into its tasks and the next id to give
+/// out, returning `None` when it does not exist.
+fn read_tasks(path: &Path) -> Result<Option<(Vec<Task>, u32)>, TasksError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let reader = io::BufReader::new(file);
+
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut ids = HashSet::new();
+    let mut version = 1;
+    let mut next_id = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let content = line?;
+        let line = index + 1;
+        let parse_error = |message| TasksError::Parse { line, message };
+
+        if index == 0 {
+            if let Some(header) = format::parse_header(&content).map_err(parse_error)? {
+                version = header.version;
+                next_id = header.next_id;
+                continue;
+            }
+        }
+
+        if content.is_empty() {
+            continue;
+        }
+
+        let task = match version {
+            1 => Task::from_legacy_string(&content),
+            _ => Task::from_string(&content),
+        };
+
+        let task = task.map_err(parse_error)?;
+
+        if task.id() != 0 && !ids.insert(task.id()) {
+            return Err(parse_error(format!("duplicate id {}", task.id())));
+        }
+
+        tasks.push(task);
+    }
+
+    let next_id = next_id.max(id_after(&tasks));
+    Ok(Some((tasks, next_id)))
+}
+
+/// Replaces the file at `path` with `content` through a synced temporary
+/// file renamed over it, so readers see either the old or the new content.
+pub(crate) fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let tmp_path = sibling_path(path, TMP_SUFFIX);
+
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(content.as_bytes())?;
+    tmp_file.sync_all()?;
+    drop(tmp_file);
+
+    fs::rename(&tmp_path, path)?;
+    sync_parent_dir(path)
+}
+
+/// Makes a rename in the parent directory durable.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
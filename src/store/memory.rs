@@ -0,0 +1,37 @@
+use crate::store::id_after;
+use crate::{Task, TaskStore, TasksError};
+
+/// Keeps tasks in memory only, for tests and for embedding the command logic.
+#[derive(Default)]
+pub struct MemoryStore {
+    tasks: Vec<Task>,
+    next_id: u32,
+}
+
+impl MemoryStore {
+    pub fn new(tasks: Vec<Task>) -> Self {
+        let next_id = id_after(&tasks);
+        Self { tasks, next_id }
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+}
+
+impl TaskStore for MemoryStore {
+    fn load(&mut self) -> Result<Vec<Task>, TasksError> {
+        Ok(self.tasks.clone())
+    }
+
+    fn save(&mut self, tasks: &[Task]) -> Result<(), TasksError> {
+        self.tasks = tasks.to_vec();
+        self.next_id = self.next_id.max(id_after(tasks));
+
+        Ok(())
+    }
+
+    fn next_id(&self) -> Result<u32, TasksError> {
+        Ok(self.next_id.max(1))
+    }
+}
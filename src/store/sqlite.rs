@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::format;
+use crate::lock::{self, DbLock};
+use crate::task::DEFAULT_LIST;
+use crate::{sibling_path, LockMode, Task, TaskStore, TasksError};
+
+/// Schema changes, applied in order. The index of a migration plus one is the
+/// version recorded in `schema_migrations`; never edit a released entry.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE tasks (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        completed INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX tasks_completed ON tasks (completed);",
+    "ALTER TABLE tasks ADD COLUMN due TEXT;
+    CREATE INDEX tasks_due ON tasks (due);",
+    "ALTER TABLE tasks ADD COLUMN priority TEXT;
+    CREATE INDEX tasks_priority ON tasks (priority);",
+    "ALTER TABLE tasks ADD COLUMN project TEXT;
+    CREATE INDEX tasks_project ON tasks (project);
+    CREATE TABLE task_tags (
+        task_id INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (task_id, tag)
+    );
+    CREATE INDEX task_tags_tag ON task_tags (tag);",
+    "ALTER TABLE tasks ADD COLUMN created TEXT;
+    ALTER TABLE tasks ADD COLUMN modified TEXT;
+    ALTER TABLE tasks ADD COLUMN completed_at TEXT;",
+    "ALTER TABLE tasks ADD COLUMN parent INTEGER;
+    CREATE INDEX tasks_parent ON tasks (parent);",
+    "CREATE TABLE task_dependencies (
+        task_id INTEGER NOT NULL,
+        depends_on INTEGER NOT NULL,
+        PRIMARY KEY (task_id, depends_on)
+    );
+    CREATE INDEX task_dependencies_depends_on ON task_dependencies (depends_on);",
+    "ALTER TABLE tasks ADD COLUMN recurrence TEXT;",
+    "ALTER TABLE tasks ADD COLUMN list TEXT;",
+    "CREATE TABLE id_sequence (next_id INTEGER NOT NULL);
+    INSERT INTO id_sequence (next_id) SELECT COALESCE(MAX(id), 0) + 1 FROM tasks;",
+];
+
+/// Tasks in a SQLite database, keyed and ordered by task id.
+///
+/// Adding, completing and deleting a task touch a single row instead of
+/// rewriting the whole list.
+pub struct SqliteStore {
+    path: PathBuf,
+    connection: Connection,
+    lock_timeout: Duration,
+    lock: Option<DbLock>,
+}
+
+impl SqliteStore {
+    /// Opens or creates the database at `path` and brings its schema up to date.
+    pub fn open(path: PathBuf) -> Result<Self, TasksError> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut connection = Connection::open(&path)?;
+        migrate(&mut connection)?;
+
+        Ok(Self {
+            path,
+            connection,
+            lock_timeout: lock::DEFAULT_TIMEOUT,
+            lock: None,
+        })
+    }
+
+    /// Sets how long `lock` waits for other processes to release the
+    /// database, and how long SQLite itself waits on a busy database.
+    pub fn with_lock_timeout(mut self, lock_timeout: Duration) -> Result<Self, TasksError> {
+        self.lock_timeout = lock_timeout;
+        self.connection.busy_timeout(lock_timeout)?;
+
+        Ok(self)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Inserts the task's rows, replacing any with the same id, and moves the id
+/// sequence past it. Callers run it in a transaction, so the task never lacks
+/// its tags or dependencies.
+fn write_row(connection: &Connection, task: &Task) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO tasks (id, name, completed, due, priority, project, created, modified, completed_at, parent,
+            recurrence, list)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            task.id(),
+            task.name(),
+            task.is_completed(),
+            task.due().map(|due| due.to_string()),
+            task.priority().map(|priority| priority.to_string()),
+            task.project(),
+            task.created().map(format::timestamp),
+            task.modified().map(format::timestamp),
+            task.completed_at().map(format::timestamp),
+            task.parent(),
+            task.recurrence().map(|recurrence| recurrence.to_string()),
+            Some(task.list()).filter(|&list| list != DEFAULT_LIST),
+        ],
+    )?;
+
+    connection.execute("DELETE FROM task_tags WHERE task_id = ?1", [task.id()])?;
+    let mut statement = connection.prepare_cached("INSERT INTO task_tags (task_id, tag) VALUES (?1, ?2)")?;
+    for tag in task.tags() {
+        statement.execute(params![task.id(), tag])?;
+    }
+
+    connection.execute("DELETE FROM task_dependencies WHERE task_id = ?1", [task.id()])?;
+    let mut statement =
+        connection.prepare_cached("INSERT INTO task_dependencies (task_id, depends_on) VALUES (?1, ?2)")?;
+    for dependency in task.dependencies() {
+        statement.execute(params![task.id(), dependency])?;
+    }
+
+    connection.execute("UPDATE id_sequence SET next_id = MAX(next_id, ?1 + 1)", [task.id()])?;
+
+    Ok(())
+}
+
+/// Reads a row selected with the columns in `write_row` order.
+fn read_row(row: &Row) -> Result<Task, TasksError> {
+    let mut task = Task::new(row.get(0)?, row.get(1)?);
+    if row.get(2)? {
+        task.complete();
+    }
+    task.set_due(parse_column(row, 3)?);
+    task.set_priority(parse_column(row, 4)?);
+    task.set_project(row.get(5)?);
+    task.set_timestamps(parse_timestamp_column(row, 6)?, parse_timestamp_column(row, 7)?, parse_timestamp_column(row, 8)?);
+    task.set_parent(row.get(9)?);
+    task.set_recurrence(parse_column(row, 10)?);
+    task.set_list(row.get(11)?);
+
+    Ok(task)
+}
+
+fn parse_column<T: FromStr<Err = TasksError>>(row: &Row, index: usize) -> Result<Option<T>, TasksError> {
+    row.get::<_, Option<String>>(index)?
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+}
+
+fn parse_timestamp_column(row: &Row, index: usize) -> Result<Option<DateTime<Utc>>, TasksError> {
+    row.get::<_, Option<String>>(index)?
+        .as_deref()
+        .map(format::parse_timestamp)
+        .transpose()
+        .map_err(|message| TasksError::Storage(message.into()))
+}
+
+impl TaskStore for SqliteStore {
+    fn load(&mut self) -> Result<Vec<Task>, TasksError> {
+        let mut tags: HashMap<u32, Vec<String>> = HashMap::new();
+        let mut statement = self.connection.prepare("SELECT task_id, tag FROM task_tags")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            tags.entry(row.get(0)?).or_default().push(row.get(1)?);
+        }
+
+        let mut dependencies: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut statement = self.connection.prepare("SELECT task_id, depends_on FROM task_dependencies")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            dependencies.entry(row.get(0)?).or_default().push(row.get(1)?);
+        }
+
+        let mut statement = self.connection.prepare(
+            "SELECT id, name, completed, due, priority, project, created, modified, completed_at, parent,
+                recurrence, list
+            FROM tasks ORDER BY id",
+        )?;
+        let mut rows = statement.query([])?;
+
+        let mut tasks = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut task = read_row(row)?;
+            for tag in tags.remove(&task.id()).unwrap_or_default() {
+                task.add_tag(&tag);
+            }
+            for dependency in dependencies.remove(&task.id()).unwrap_or_default() {
+                task.add_dependency(dependency);
+            }
+            tasks.push(task);
+        }
+
+        Ok(tasks)
+    }
+
+    fn save(&mut self, tasks: &[Task]) -> Result<(), TasksError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM tasks", [])?;
+        transaction.execute("DELETE FROM task_tags", [])?;
+        transaction.execute("DELETE FROM task_dependencies", [])?;
+
+        for task in tasks {
+            write_row(&transaction, task)?;
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn insert(&mut self, tasks: &[Task], index: usize) -> Result<(), TasksError> {
+        let transaction = self.connection.transaction()?;
+        write_row(&transaction, &tasks[index])?;
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn update(&mut self, tasks: &[Task], index: usize) -> Result<(), TasksError> {
+        let transaction = self.connection.transaction()?;
+        write_row(&transaction, &tasks[index])?;
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn delete(&mut self, _tasks: &[Task], removed: &Task) -> Result<(), TasksError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM tasks WHERE id = ?1", [removed.id()])?;
+        transaction.execute("DELETE FROM task_tags WHERE task_id = ?1", [removed.id()])?;
+        transaction.execute("DELETE FROM task_dependencies WHERE task_id = ?1", [removed.id()])?;
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn next_id(&self) -> Result<u32, TasksError> {
+        Ok(self.connection.query_row("SELECT next_id FROM id_sequence", [], |row| row.get(0))?)
+    }
+
+    fn sidecar_path(&self, suffix: &str) -> Option<PathBuf> {
+        Some(sibling_path(&self.path, suffix))
+    }
+
+    fn lock(&mut self, mode: LockMode) -> Result<(), TasksError> {
+        self.lock = Some(DbLock::acquire(&self.path, mode, self.lock_timeout)?);
+
+        Ok(())
+    }
+}
+
+/// Applies every migration newer than the database's recorded version.
+fn migrate(connection: &mut Connection) -> Result<(), TasksError> {
+    connection.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+
+    let current: usize = connection
+        .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get::<_, Option<usize>>(0))
+        .optional()?
+        .flatten()
+        .unwrap_or(0);
+
+    if current > MIGRATIONS.len() {
+        return Err(TasksError::Unsupported(format!(
+            "Database schema version {current} is newer than this program supports ({})",
+            MIGRATIONS.len(),
+        )));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.execute("INSERT INTO schema_migrations (version) VALUES (?1)", [index + 1])?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
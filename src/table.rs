@@ -0,0 +1,190 @@
+//! Aligned text tables, sized to the terminal.
+
+use std::env;
+
+use terminal_size::{terminal_size, Width};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::color::Style;
+
+const COLUMN_GAP: &str = "  ";
+const ELLIPSIS: char = '…';
+/// The narrowest the flexible column gets; below that the table runs over
+/// the terminal width instead.
+const MIN_FLEXIBLE_WIDTH: usize = 10;
+
+/// The width to fit tables in: `$COLUMNS` if set, else the width of the
+/// terminal on standard output. `None` when writing elsewhere, in which
+/// case tables are not limited.
+pub fn terminal_width() -> Option<usize> {
+    if let Some(columns) = env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()) {
+        return Some(columns);
+    }
+
+    terminal_size().map(|(Width(width), _)| usize::from(width))
+}
+
+pub enum Line {
+    /// Text printed as is, such as a group heading or a blank line.
+    Text(String),
+    /// The column headings.
+    Header,
+    Row(Vec<Cell>),
+}
+
+pub struct Cell {
+    text: String,
+    style: Style,
+}
+
+impl Cell {
+    pub fn new(text: String, style: Style) -> Self {
+        Self { text, style }
+    }
+}
+
+pub struct Table {
+    headings: Vec<&'static str>,
+    /// The column that gives up space when the table is too wide,
+    /// truncating or wrapping its cells.
+    flexible: Option<usize>,
+    lines: Vec<Line>,
+}
+
+impl Table {
+    pub fn new(headings: Vec<&'static str>, flexible: Option<usize>) -> Self {
+        Self { headings, flexible, lines: Vec::new() }
+    }
+
+    pub fn push(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+
+    /// Lays the table out within `max_width` columns, if given. Cells of the
+    /// flexible column that do not fit are wrapped onto more lines when
+    /// `wrap` is set, or cut short with an ellipsis.
+    pub fn render(&self, max_width: Option<usize>, wrap: bool) -> Vec<String> {
+        let mut widths: Vec<usize> = self.headings.iter().map(|heading| heading.width()).collect();
+        for line in &self.lines {
+            if let Line::Row(cells) = line {
+                for (width, cell) in widths.iter_mut().zip(cells) {
+                    *width = (*width).max(cell.text.width());
+                }
+            }
+        }
+
+        if let (Some(max_width), Some(flexible)) = (max_width, self.flexible) {
+            let total = widths.iter().sum::<usize>() + COLUMN_GAP.len() * widths.len().saturating_sub(1);
+            if total > max_width {
+                let shrunk = widths[flexible].saturating_sub(total - max_width).max(MIN_FLEXIBLE_WIDTH);
+                widths[flexible] = widths[flexible].min(shrunk);
+            }
+        }
+
+        let mut rendered = Vec::new();
+        for line in &self.lines {
+            match line {
+                Line::Text(text) => rendered.push(text.clone()),
+                Line::Header => {
+                    let cells: Vec<Cell> =
+                        self.headings.iter().map(|heading| Cell::new(heading.to_string(), Style::default())).collect();
+                    rendered.extend(self.render_row(&cells, &widths, wrap));
+                },
+                Line::Row(cells) => rendered.extend(self.render_row(cells, &widths, wrap)),
+            }
+        }
+
+        rendered
+    }
+
+    /// One or, with wrapping, several lines for a row, each cell styled and
+    /// padded to its column's width.
+    fn render_row(&self, cells: &[Cell], widths: &[usize], wrap: bool) -> Vec<String> {
+        let columns: Vec<Vec<String>> = cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(index, (cell, &width))| match Some(index) == self.flexible && cell.text.width() > width {
+                false => vec![cell.text.clone()],
+                true if wrap => wrap_text(&cell.text, width),
+                true => vec![truncate(&cell.text, width)],
+            })
+            .collect();
+
+        let height = columns.iter().map(Vec::len).max().unwrap_or(1);
+
+        (0..height)
+            .map(|line| {
+                let padded: Vec<String> = columns
+                    .iter()
+                    .zip(cells)
+                    .zip(widths)
+                    .map(|((cell_lines, cell), &width)| {
+                        pad(cell_lines.get(line).map(String::as_str).unwrap_or_default(), &cell.style, width)
+                    })
+                    .collect();
+                padded.join(COLUMN_GAP).trim_end().to_string()
+            })
+            .collect()
+    }
+}
+
+/// Styles `text`, then pads it with plain spaces so the padding is never
+/// underlined or given a background.
+fn pad(text: &str, style: &Style, width: usize) -> String {
+    format!("{}{}", style.paint(text), " ".repeat(width.saturating_sub(text.width())))
+}
+
+/// Cuts `text` to at most `width` columns, ending it with an ellipsis.
+fn truncate(text: &str, width: usize) -> String {
+    let mut truncated = String::new();
+    let mut used = 0;
+
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + ELLIPSIS.width().unwrap_or(1) > width {
+            break;
+        }
+        truncated.push(c);
+        used += char_width;
+    }
+
+    truncated.push(ELLIPSIS);
+    truncated
+}
+
+/// Breaks `text` into lines of at most `width` columns at spaces, splitting
+/// words longer than a line. Every line keeps the text's leading
+/// indentation, so wrapped subtasks stay under their parent.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let body = text.trim_start_matches(' ');
+    let indent = &text[..text.len() - body.len()];
+    let available = width.saturating_sub(indent.len()).max(1);
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for word in body.split(' ').filter(|word| !word.is_empty()) {
+        if !current.is_empty() && current.width() + 1 + word.width() <= available {
+            current.push(' ');
+            current.push_str(word);
+            continue;
+        }
+
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+
+        for c in word.chars() {
+            if current.width() + c.width().unwrap_or(0) > available && !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+
+    lines.into_iter().map(|line| format!("{indent}{line}")).collect()
+}
@@ -0,0 +1,452 @@
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::{format, Due, Priority, Recurrence, TasksError, SEPARATOR};
+
+pub const TAG_PREFIX: char = '+';
+pub const PROJECT_PREFIX: &str = "project:";
+const LIST_SEPARATOR: char = ',';
+/// The list of tasks stored without a `list` attribute.
+pub const DEFAULT_LIST: &str = "inbox";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Task {
+    id: u32,
+    name: String,
+    completed: bool,
+    due: Option<Due>,
+    priority: Option<Priority>,
+    /// Kept sorted and free of duplicates.
+    tags: Vec<String>,
+    project: Option<String>,
+    /// The named list holding the task; `None` for the default list.
+    list: Option<String>,
+    /// The id of the task this one is a step of.
+    parent: Option<u32>,
+    /// Ids of the tasks that must be done first, sorted and free of
+    /// duplicates.
+    dependencies: Vec<u32>,
+    recurrence: Option<Recurrence>,
+    /// Missing for tasks written before timestamps were recorded.
+    created: Option<DateTime<Utc>>,
+    modified: Option<DateTime<Utc>>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+impl Task {
+    /// Creates a pending task. An `id` of 0 means none has been assigned yet;
+    /// `run` numbers such tasks after the highest existing id.
+    pub fn new(id: u32, name: String) -> Self {
+        Self {
+            id,
+            name,
+            completed: false,
+            due: None,
+            priority: None,
+            tags: Vec::new(),
+            project: None,
+            list: None,
+            parent: None,
+            dependencies: Vec::new(),
+            recurrence: None,
+            created: None,
+            modified: None,
+            completed_at: None,
+        }
+    }
+
+    /// Creates a pending task from free text, taking `+tag` and
+    /// `project:name` words out of it.
+    pub fn from_description(id: u32, description: &str) -> Self {
+        let mut task = Self::new(id, String::new());
+        task.name = task.take_attributes(description);
+        task
+    }
+
+    /// Applies the `+tag` and `project:name` words of `description`,
+    /// returning the remaining words.
+    fn take_attributes(&mut self, description: &str) -> String {
+        let mut words = Vec::new();
+
+        for word in description.split_whitespace() {
+            if let Some(tag) = word.strip_prefix(TAG_PREFIX).filter(|tag| is_valid_tag(tag)) {
+                self.add_tag(tag);
+            } else if let Some(project) = word.strip_prefix(PROJECT_PREFIX).filter(|project| !project.is_empty()) {
+                self.project = Some(project.to_string());
+            } else {
+                words.push(word);
+            }
+        }
+
+        words.join(" ")
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub(crate) fn set_id(&mut self, id: u32) {
+        self.id = id;
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    /// Replaces the name with the free text of `description`; its tags are
+    /// added and its project replaces the current one.
+    pub fn edit(&mut self, description: &str) {
+        self.name = self.take_attributes(description);
+    }
+
+    /// Adds the free text of `description` after the name.
+    pub fn append(&mut self, description: &str) {
+        let text = self.take_attributes(description);
+        self.name = [self.name.as_str(), &text].join(" ").trim().to_string();
+    }
+
+    /// Adds the free text of `description` before the name.
+    pub fn prepend(&mut self, description: &str) {
+        let text = self.take_attributes(description);
+        self.name = [text.as_str(), &self.name].join(" ").trim().to_string();
+    }
+
+    pub fn complete(&mut self) {
+        self.completed = true;
+    }
+
+    /// Marks the task pending again.
+    pub fn reopen(&mut self) {
+        self.completed = false;
+    }
+
+    pub fn due(&self) -> Option<Due> {
+        self.due
+    }
+
+    pub fn set_due(&mut self, due: Option<Due>) {
+        self.due = due;
+    }
+
+    pub fn priority(&self) -> Option<Priority> {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, priority: Option<Priority>) {
+        self.priority = priority;
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.binary_search_by(|existing| existing.as_str().cmp(tag)).is_ok()
+    }
+
+    /// Adds `tag` unless the task already has it.
+    pub fn add_tag(&mut self, tag: &str) {
+        if let Err(index) = self.tags.binary_search_by(|existing| existing.as_str().cmp(tag)) {
+            self.tags.insert(index, tag.to_string());
+        }
+    }
+
+    pub fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    pub fn set_project(&mut self, project: Option<String>) {
+        self.project = project;
+    }
+
+    /// The name of the list holding the task.
+    pub fn list(&self) -> &str {
+        self.list.as_deref().unwrap_or(DEFAULT_LIST)
+    }
+
+    /// Moves the task to the named list; the default list is stored as none.
+    pub fn set_list(&mut self, list: Option<String>) {
+        self.list = list.filter(|list| list != DEFAULT_LIST);
+    }
+
+    pub fn parent(&self) -> Option<u32> {
+        self.parent
+    }
+
+    pub fn set_parent(&mut self, parent: Option<u32>) {
+        self.parent = parent;
+    }
+
+    pub fn dependencies(&self) -> &[u32] {
+        &self.dependencies
+    }
+
+    /// Records that this task depends on the task `id`, unless it already does.
+    pub fn add_dependency(&mut self, id: u32) {
+        if let Err(index) = self.dependencies.binary_search(&id) {
+            self.dependencies.insert(index, id);
+        }
+    }
+
+    pub fn remove_dependency(&mut self, id: u32) {
+        self.dependencies.retain(|&dependency| dependency != id);
+    }
+
+    pub fn clear_dependencies(&mut self) {
+        self.dependencies.clear();
+    }
+
+    pub fn recurrence(&self) -> Option<&Recurrence> {
+        self.recurrence.as_ref()
+    }
+
+    pub fn set_recurrence(&mut self, recurrence: Option<Recurrence>) {
+        self.recurrence = recurrence;
+    }
+
+    /// The pending instance that follows this recurring task when it is
+    /// completed on `completed`, or `None` if the task does not recur. It
+    /// keeps the name and attributes but not the id, dependencies or
+    /// timestamps, and its due date is rolled forward. Fails when that date
+    /// is out of range.
+    pub fn next_occurrence(&self, completed: NaiveDate) -> Result<Option<Task>, TasksError> {
+        let Some(recurrence) = self.recurrence.as_ref() else {
+            return Ok(None);
+        };
+        let due = match self.due {
+            Some(due) => Due::new(recurrence.next(due.date, completed)?, due.time),
+            None => Due::new(recurrence.next(completed, completed)?, None),
+        };
+
+        let mut next = Task::new(0, self.name.clone());
+        next.due = Some(due);
+        next.priority = self.priority;
+        next.tags = self.tags.clone();
+        next.project = self.project.clone();
+        next.list = self.list.clone();
+        next.parent = self.parent;
+        next.recurrence = Some(recurrence.clone());
+
+        Ok(Some(next))
+    }
+
+    pub fn created(&self) -> Option<DateTime<Utc>> {
+        self.created
+    }
+
+    pub fn modified(&self) -> Option<DateTime<Utc>> {
+        self.modified
+    }
+
+    pub fn completed_at(&self) -> Option<DateTime<Utc>> {
+        self.completed_at
+    }
+
+    /// Sets all three timestamps, e.g. when reading a task back from a store.
+    pub fn set_timestamps(
+        &mut self,
+        created: Option<DateTime<Utc>>,
+        modified: Option<DateTime<Utc>>,
+        completed_at: Option<DateTime<Utc>>,
+    ) {
+        self.created = created;
+        self.modified = modified;
+        self.completed_at = completed_at;
+    }
+
+    /// Stamps a task added at `now`.
+    pub(crate) fn stamp_created(&mut self, now: DateTime<Utc>) {
+        self.created = Some(now);
+        self.modified = Some(now);
+        self.completed_at = if self.completed { Some(now) } else { None };
+    }
+
+    /// Stamps a change made at `now`, recording the completion time when it
+    /// completed the task and clearing it when it reopened the task.
+    pub(crate) fn stamp_modified(&mut self, now: DateTime<Utc>, was_completed: bool) {
+        self.modified = Some(now);
+
+        if !self.completed {
+            self.completed_at = None;
+        } else if !was_completed {
+            self.completed_at = Some(now);
+        }
+    }
+
+    /// Parses a version 2 or 3 line.
+    pub(crate) fn from_string(content: &str) -> Result<Self, String> {
+        let fields = format::split(content)?;
+
+        let [completed, name, attributes @ ..] = fields.as_slice() else {
+            return Err(format!("expected at least 2 fields, found {}", fields.len()));
+        };
+
+        let mut task = Self::new(0, name.clone());
+        task.completed = parse_completed(completed)?;
+
+        for attribute in attributes {
+            let (key, value) = format::split_attribute(attribute)?;
+
+            match key {
+                "id" => task.id = parse_id(value)?,
+                "due" => task.due = Some(value.parse().map_err(|_| format!("invalid due date {value:?}"))?),
+                "priority" => task.priority = Some(value.parse().map_err(|_| format!("invalid priority {value:?}"))?),
+                "tags" => {
+                    for tag in value.split(LIST_SEPARATOR) {
+                        if !is_valid_tag(tag) {
+                            return Err(format!("invalid tag {tag:?}"));
+                        }
+                        task.add_tag(tag);
+                    }
+                },
+                "project" => task.project = Some(value.to_string()),
+                "list" => task.set_list(Some(value.to_string())),
+                "parent" => task.parent = Some(parse_id(value)?),
+                "depends" => {
+                    for id in value.split(LIST_SEPARATOR) {
+                        task.add_dependency(parse_id(id)?);
+                    }
+                },
+                "recur" => task.recurrence = Some(value.parse().map_err(|_| format!("invalid recurrence {value:?}"))?),
+                "created" => task.created = Some(format::parse_timestamp(value)?),
+                "modified" => task.modified = Some(format::parse_timestamp(value)?),
+                "completed_at" => task.completed_at = Some(format::parse_timestamp(value)?),
+                _ => return Err(format!("unknown attribute {key:?}")),
+            }
+        }
+
+        Ok(task)
+    }
+
+    /// Parses a version 1 line, where everything after the first separator
+    /// is the unescaped name.
+    pub(crate) fn from_legacy_string(content: &str) -> Result<Self, String> {
+        let (completed, name) = content
+            .split_once(SEPARATOR)
+            .ok_or_else(|| format!("missing {SEPARATOR:?} separator"))?;
+
+        let mut task = Self::new(0, name.to_string());
+        task.completed = parse_completed(completed)?;
+
+        Ok(task)
+    }
+}
+
+fn parse_completed(flag: &str) -> Result<bool, String> {
+    match flag {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(format!("invalid completion flag {flag:?}, expected 0 or 1")),
+    }
+}
+
+/// Tags are single words that fit in the comma-separated `tags` attribute.
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && !tag.contains(|c: char| c.is_whitespace() || c == LIST_SEPARATOR)
+}
+
+/// Checks a list name, which must be a single word.
+pub fn parse_list(list: &str) -> Result<String, TasksError> {
+    if list.is_empty() || list.contains(char::is_whitespace) {
+        return Err(TasksError::InvalidArgument(format!("Invalid list name {list:?}, expected a single word")));
+    }
+
+    Ok(list.to_string())
+}
+
+fn parse_id(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(id) if id > 0 => Ok(id),
+        _ => Err(format!("invalid id {value:?}, expected a positive integer")),
+    }
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let completed = if self.completed { 1 } else { 0 };
+        write!(f, "{}{}{}", completed, SEPARATOR, format::escape(&self.name))?;
+
+        let mut attributes = vec![format::attribute("id", self.id)];
+        if let Some(due) = self.due {
+            attributes.push(format::attribute("due", due));
+        }
+        if let Some(priority) = self.priority {
+            attributes.push(format::attribute("priority", priority));
+        }
+        if !self.tags.is_empty() {
+            attributes.push(format::attribute("tags", self.tags.join(&LIST_SEPARATOR.to_string())));
+        }
+        if let Some(project) = &self.project {
+            attributes.push(format::attribute("project", project));
+        }
+        if let Some(list) = &self.list {
+            attributes.push(format::attribute("list", list));
+        }
+        if let Some(parent) = self.parent {
+            attributes.push(format::attribute("parent", parent));
+        }
+        if !self.dependencies.is_empty() {
+            let ids: Vec<String> = self.dependencies.iter().map(u32::to_string).collect();
+            attributes.push(format::attribute("depends", ids.join(&LIST_SEPARATOR.to_string())));
+        }
+        if let Some(recurrence) = &self.recurrence {
+            attributes.push(format::attribute("recur", recurrence));
+        }
+        if let Some(created) = self.created {
+            attributes.push(format::attribute("created", format::timestamp(created)));
+        }
+        if let Some(modified) = self.modified {
+            attributes.push(format::attribute("modified", format::timestamp(modified)));
+        }
+        if let Some(completed_at) = self.completed_at {
+            attributes.push(format::attribute("completed_at", format::timestamp(completed_at)));
+        }
+
+        for attribute in attributes {
+            write!(f, "{}{}", SEPARATOR, format::escape(&attribute))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_line_keeps_separators_in_name() {
+        let task = Task::from_legacy_string("1|Fix a|b \\ c").unwrap();
+
+        assert!(task.is_completed());
+        assert_eq!(task.name(), "Fix a|b \\ c");
+        assert_eq!(task.id(), 0);
+    }
+
+    #[test]
+    fn legacy_line_errors() {
+        assert_eq!(Task::from_legacy_string("Buy milk").unwrap_err(), "missing '|' separator");
+        assert_eq!(Task::from_legacy_string("x|Buy milk").unwrap_err(), "invalid completion flag \"x\", expected 0 or 1");
+    }
+
+    #[test]
+    fn line_round_trips() {
+        let line = "0|Call \\|Bob\\||id:4|due:2026-10-20|priority:H|tags:home,phone|project:family";
+        let task = Task::from_string(line).unwrap();
+
+        assert_eq!(task.id(), 4);
+        assert_eq!(task.name(), "Call |Bob|");
+        assert_eq!(task.tags(), ["home", "phone"]);
+        assert_eq!(Task::from_string(&task.to_string()).unwrap().to_string(), task.to_string());
+    }
+
+    #[test]
+    fn unknown_attribute_is_an_error() {
+        assert_eq!(Task::from_string("0|Buy milk|color:red").unwrap_err(), "unknown attribute \"color\"");
+    }
+}
@@ -0,0 +1,28 @@
+mod common;
+
+use std::fs;
+
+use common::{scratch_dir, todos};
+use todos::{FileStore, TaskStore};
+
+#[test]
+fn undo_takes_restored_tasks_out_of_the_archive() {
+    let dir = scratch_dir("undo-archived");
+    let db = dir.join("t.txt");
+    fs::write(dir.join("t.txt.config"), "archive.after = 0d\n").unwrap();
+
+    todos(&db, &["add", "Buy milk"]).unwrap();
+    todos(&db, &["complete", "1"]).unwrap();
+
+    let archived = FileStore::new(dir.join("t.txt.archive")).load().unwrap();
+    assert_eq!(archived.iter().map(|task| task.id()).collect::<Vec<_>>(), [1]);
+
+    todos(&db, &["undo"]).unwrap();
+
+    let tasks = FileStore::new(db.clone()).load().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert!(!tasks[0].is_completed());
+    assert!(FileStore::new(dir.join("t.txt.archive")).load().unwrap().is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
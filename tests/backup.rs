@@ -0,0 +1,63 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{scratch_dir, todos};
+use todos::{FileStore, TaskStore};
+
+/// Adds two tasks, so the backup holds the first and the database both.
+fn add_two(db: &Path) {
+    todos(db, &["add", "Buy milk"]).unwrap();
+    todos(db, &["add", "Call mom"]).unwrap();
+}
+
+/// The names of the tasks loaded from `db`, and the warnings the load left.
+fn load(db: &Path) -> (Vec<String>, Vec<String>) {
+    let mut store = FileStore::new(db.to_path_buf());
+    let names = store.load().unwrap().iter().map(|task| task.name().to_string()).collect();
+    (names, store.take_warnings())
+}
+
+#[test]
+fn corrupt_database_is_recovered_from_the_backup() {
+    let dir = scratch_dir("backup-corrupt");
+    let db = dir.join("t.txt");
+    add_two(&db);
+
+    fs::write(&db, "# tasks v3\n0|Half a line|id:x\n").unwrap();
+
+    let (names, warnings) = load(&db);
+    assert_eq!(names, ["Buy milk"]);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("is unreadable"), "{warnings:?}");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_database_is_recovered_from_the_backup() {
+    let dir = scratch_dir("backup-missing");
+    let db = dir.join("t.txt");
+    add_two(&db);
+
+    fs::remove_file(&db).unwrap();
+
+    let (names, warnings) = load(&db);
+    assert_eq!(names, ["Buy milk"]);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("is missing"), "{warnings:?}");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn corrupt_database_without_a_backup_fails() {
+    let dir = scratch_dir("backup-none");
+    let db = dir.join("t.txt");
+    fs::write(&db, "# tasks v3\n0|Half a line|id:x\n").unwrap();
+
+    assert!(FileStore::new(db).load().is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
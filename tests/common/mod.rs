@@ -0,0 +1,53 @@
+// Each test crate uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use todos::{Command, MemoryStore, Options, Task, TaskStore, TasksError};
+
+/// A fresh directory for one test, removed first if a previous run left it.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("todos-test-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs a command line, such as `["complete", "1"]`, against the database
+/// at `db`.
+pub fn todos(db: &Path, args: &[&str]) -> Result<(), TasksError> {
+    let mut line = vec!["todos".to_string(), "--db".to_string(), db.display().to_string()];
+    line.extend(args.iter().map(|arg| arg.to_string()));
+
+    let (options, args) = Options::build(&line)?;
+    todos::run_cli(&options, Command::build(args)?)
+}
+
+/// A store in memory that counts how often it is written.
+#[derive(Default)]
+pub struct CountingStore {
+    pub inner: MemoryStore,
+    pub writes: usize,
+}
+
+impl TaskStore for CountingStore {
+    fn load(&mut self) -> Result<Vec<Task>, TasksError> {
+        self.inner.load()
+    }
+
+    fn save(&mut self, tasks: &[Task]) -> Result<(), TasksError> {
+        self.writes += 1;
+        self.inner.save(tasks)
+    }
+
+    fn next_id(&self) -> Result<u32, TasksError> {
+        self.inner.next_id()
+    }
+}
+
+/// Runs a command line against `store`, which must succeed.
+pub fn run(store: &mut CountingStore, line: &[&str]) {
+    let args: Vec<String> = line.iter().map(|arg| arg.to_string()).collect();
+    todos::run(Command::build(&args).unwrap(), store).unwrap();
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use std::fs;
+
+use common::{scratch_dir, todos};
+use todos::{Config, TasksError};
+
+#[test]
+fn set_keeps_comments_and_order() {
+    let dir = scratch_dir("config-comments");
+    let path = dir.join("t.txt.config");
+    fs::write(&path, "# archive weekly\narchive.after = 7d\n\n# work first\nlist.default = work\n").unwrap();
+
+    let mut config = Config::load(Some(path.clone())).unwrap();
+    config.set("archive.after", Some("30d")).unwrap();
+    config.set("list.columns", Some("id,task")).unwrap();
+    config.set("list.default", None).unwrap();
+
+    let expected = "# archive weekly\narchive.after = 30d\n\n# work first\nlist.columns = id,task\n";
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+    assert_eq!(Config::load(Some(path)).unwrap().get("archive.after"), Some("30d"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn bad_line_is_an_invalid_setting() {
+    let dir = scratch_dir("config-invalid");
+    let db = dir.join("t.txt");
+    fs::write(dir.join("t.txt.config"), "# settings\narchive.after = soon\n").unwrap();
+
+    let err = todos(&db, &["list"]).unwrap_err();
+    assert!(matches!(err, TasksError::InvalidArgument(_)), "{err:?}");
+    assert!(err.to_string().contains("line 2"), "{err}");
+
+    fs::write(dir.join("t.txt.config"), "no equals sign\n").unwrap();
+    assert!(matches!(todos(&db, &["list"]), Err(TasksError::InvalidArgument(_))));
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use std::fs;
+
+use common::{run, scratch_dir, todos, CountingStore};
+use todos::{FileStore, TaskStore};
+
+#[test]
+fn deleting_and_keeping_subtasks_saves_once() {
+    let mut store = CountingStore::default();
+    run(&mut store, &["add", "Plan"]);
+    run(&mut store, &["add", "Draft", "--parent", "1"]);
+    run(&mut store, &["add", "Review", "--parent", "2"]);
+    run(&mut store, &["add", "Ship"]);
+
+    store.writes = 0;
+    run(&mut store, &["delete", "%0,%2", "--subtasks=keep", "--yes"]);
+    assert_eq!(store.writes, 1);
+
+    let remaining: Vec<(u32, &str, Option<u32>)> =
+        store.inner.tasks().iter().map(|task| (task.id(), task.name(), task.parent())).collect();
+    assert_eq!(remaining, [(2, "Draft", None), (4, "Ship", None)]);
+}
+
+#[test]
+fn deleting_removes_the_task_from_dependencies() {
+    let mut store = CountingStore::default();
+    for name in ["Design", "Build", "Ship"] {
+        run(&mut store, &["add", name]);
+    }
+    run(&mut store, &["depends", "2", "on", "1"]);
+    run(&mut store, &["depends", "3", "on", "1,2"]);
+
+    store.writes = 0;
+    run(&mut store, &["delete", "1", "--yes"]);
+    assert_eq!(store.writes, 1);
+
+    let dependencies: Vec<(u32, Vec<u32>)> =
+        store.inner.tasks().iter().map(|task| (task.id(), task.dependencies().to_vec())).collect();
+    assert_eq!(dependencies, [(2, vec![]), (3, vec![2])]);
+}
+
+#[test]
+fn undoing_a_delete_restores_dependencies() {
+    let dir = scratch_dir("delete-dependencies");
+    let db = dir.join("t.txt");
+    todos(&db, &["add", "Design"]).unwrap();
+    todos(&db, &["add", "Build"]).unwrap();
+    todos(&db, &["depends", "2", "on", "1"]).unwrap();
+
+    todos(&db, &["delete", "1", "--yes"]).unwrap();
+    assert!(FileStore::new(db.clone()).load().unwrap()[0].dependencies().is_empty());
+
+    todos(&db, &["undo"]).unwrap();
+    let tasks = FileStore::new(db.clone()).load().unwrap();
+    assert_eq!(tasks.iter().map(|task| task.id()).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(tasks[1].dependencies(), [1]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,50 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use common::{scratch_dir, todos};
+use todos::{FileStore, TaskStore, TasksError};
+
+/// Writes an executable editor script that runs `body` with the file to
+/// edit as `$1`.
+fn editor(dir: &Path, name: &str, body: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn name(db: &Path) -> String {
+    FileStore::new(db.to_path_buf()).load().unwrap()[0].name().to_string()
+}
+
+// Both cases share one test, as the editor is picked from the environment.
+#[test]
+fn editing_in_the_editor_checks_for_changes_made_meanwhile() {
+    let dir = scratch_dir("edit");
+    let db = dir.join("t.txt");
+    todos(&db, &["add", "Draft"]).unwrap();
+
+    let rename = editor(&dir, "rename.sh", "printf 'Renamed\\n' > \"$1\"");
+    env::set_var("VISUAL", &rename);
+    todos(&db, &["edit", "1"]).unwrap();
+    assert_eq!(name(&db), "Renamed");
+
+    // This editor changes the task in the database while it is open.
+    let body = format!(
+        "printf '# tasks v3 next:2\\n0|Changed|id:1|modified:2030-01-01T00:00:00Z\\n' > {}\nprintf 'Mine\\n' > \"$1\"",
+        db.display(),
+    );
+    let racing = editor(&dir, "racing.sh", &body);
+    env::set_var("VISUAL", &racing);
+    assert!(matches!(todos(&db, &["edit", "1"]), Err(TasksError::InvalidArgument(_))));
+    assert_eq!(name(&db), "Changed");
+
+    env::remove_var("VISUAL");
+    fs::remove_dir_all(dir).unwrap();
+}
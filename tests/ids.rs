@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{scratch_dir, todos};
+use todos::{FileStore, TaskStore};
+
+fn delete_highest_then_add(db: &Path) {
+    for name in ["a", "b", "c"] {
+        todos(db, &["add", name]).unwrap();
+    }
+    todos(db, &["delete", "3", "--yes"]).unwrap();
+    todos(db, &["add", "d"]).unwrap();
+}
+
+fn ids(store: &mut dyn TaskStore) -> Vec<(u32, String)> {
+    store.load().unwrap().iter().map(|task| (task.id(), task.name().to_string())).collect()
+}
+
+#[test]
+fn deleted_ids_are_not_reused() {
+    let dir = scratch_dir("ids-text");
+    let db = dir.join("t.txt");
+
+    delete_highest_then_add(&db);
+
+    let found = ids(&mut FileStore::new(db.clone()));
+    assert_eq!(found, [(1, "a".into()), (2, "b".into()), (4, "d".into())]);
+    assert!(fs::read_to_string(&db).unwrap().starts_with("# tasks v3 next:5\n"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn deleted_ids_are_not_reused_in_sqlite() {
+    let dir = scratch_dir("ids-sqlite");
+    let db = dir.join("t.sqlite");
+
+    delete_highest_then_add(&db);
+
+    let found = ids(&mut todos::SqliteStore::open(db).unwrap());
+    assert_eq!(found, [(1, "a".into()), (2, "b".into()), (4, "d".into())]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{scratch_dir, todos};
+use todos::{FileStore, Journal, TaskStore, TasksError};
+
+/// Each task as id, name and whether it is completed.
+fn tasks(db: &Path) -> Vec<(u32, String, bool)> {
+    let tasks = FileStore::new(db.to_path_buf()).load().unwrap();
+    tasks.iter().map(|task| (task.id(), task.name().to_string(), task.is_completed())).collect()
+}
+
+#[test]
+fn undo_redo_and_a_new_operation() {
+    let dir = scratch_dir("journal");
+    let db = dir.join("t.txt");
+    for name in ["Design", "Build", "Ship"] {
+        todos(&db, &["add", name]).unwrap();
+    }
+
+    // The edited name is longer than the chunks the journal is read back in.
+    let long_name = "Ship it ".repeat(2000);
+    todos(&db, &["complete", "1"]).unwrap();
+    todos(&db, &["delete", "2", "--yes"]).unwrap();
+    todos(&db, &["edit", "3", long_name.trim_end()]).unwrap();
+
+    todos(&db, &["undo", "2"]).unwrap();
+    assert_eq!(
+        tasks(&db),
+        [(1, "Design".into(), true), (2, "Build".into(), false), (3, "Ship".into(), false)],
+    );
+
+    todos(&db, &["redo"]).unwrap();
+    assert_eq!(tasks(&db), [(1, "Design".into(), true), (3, "Ship".into(), false)]);
+
+    // A new operation discards the edit that is still undone.
+    todos(&db, &["complete", "3"]).unwrap();
+    assert!(matches!(todos(&db, &["redo"]), Err(TasksError::InvalidArgument(_))));
+    assert_eq!(tasks(&db), [(1, "Design".into(), true), (3, "Ship".into(), true)]);
+
+    let (applied, undone) = Journal::new(dir.join("t.txt.journal")).stacks().unwrap();
+    let numbers: Vec<u32> = applied.iter().map(|operation| operation.number).collect();
+    assert_eq!(numbers, [1, 2, 3, 4, 5, 7]);
+    assert!(undone.is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{scratch_dir, todos};
+use todos::{FileStore, TaskStore};
+
+/// Writes a version 1 file, which has no header and no ids.
+fn write_v1(dir: &Path) -> PathBuf {
+    let source = dir.join("old.txt");
+    fs::write(&source, "0|Buy milk\n1|Call mom\n0|Pay rent\n").unwrap();
+    source
+}
+
+fn assert_migrated(store: &mut dyn TaskStore) {
+    let tasks = store.load().unwrap();
+    let found: Vec<(u32, &str, bool)> = tasks.iter().map(|task| (task.id(), task.name(), task.is_completed())).collect();
+    assert_eq!(found, [(1, "Buy milk", false), (2, "Call mom", true), (3, "Pay rent", false)]);
+}
+
+#[test]
+fn migrating_v1_file_numbers_tasks() {
+    let dir = scratch_dir("v1-to-text");
+    let source = write_v1(&dir);
+    let target = dir.join("tasks.txt");
+
+    todos(&target, &["migrate", source.to_str().unwrap()]).unwrap();
+    // The migrated file must load again, as with the next command.
+    todos(&target, &["list"]).unwrap();
+
+    assert_migrated(&mut FileStore::new(target));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn migrating_v1_file_into_sqlite_keeps_every_task() {
+    let dir = scratch_dir("v1-to-sqlite");
+    let source = write_v1(&dir);
+    let target = dir.join("tasks.sqlite");
+
+    todos(&target, &["migrate", source.to_str().unwrap()]).unwrap();
+
+    assert_migrated(&mut todos::SqliteStore::open(target).unwrap());
+    fs::remove_dir_all(dir).unwrap();
+}
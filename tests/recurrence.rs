@@ -0,0 +1,38 @@
+mod common;
+
+use common::{run, CountingStore};
+use todos::{Command, TasksError};
+
+#[test]
+fn completing_recurring_tasks_saves_once() {
+    let mut store = CountingStore::default();
+    run(&mut store, &["add", "Water", "plants", "--recur", "daily"]);
+    run(&mut store, &["add", "Pay", "rent", "--recur", "monthly:1"]);
+    run(&mut store, &["add", "Stretch", "--recur", "after:2d"]);
+
+    store.writes = 0;
+    run(&mut store, &["complete", "1-3", "--yes"]);
+    assert_eq!(store.writes, 1);
+
+    let tasks = store.inner.tasks();
+    let summary: Vec<(u32, bool, bool)> =
+        tasks.iter().map(|task| (task.id(), task.is_completed(), task.recurrence().is_some())).collect();
+    assert_eq!(
+        summary,
+        [(1, true, false), (2, true, false), (3, true, false), (4, false, true), (5, false, true), (6, false, true)],
+    );
+    assert_eq!(tasks[3].name(), "Water plants");
+}
+
+#[test]
+fn completing_past_the_last_date_fails_without_saving() {
+    let mut store = CountingStore::default();
+    run(&mut store, &["add", "Someday", "--recur", "after:99999999d"]);
+
+    store.writes = 0;
+    let args: Vec<String> = ["complete", "1"].iter().map(|arg| arg.to_string()).collect();
+    let result = todos::run(Command::build(&args).unwrap(), &mut store);
+    assert!(matches!(result, Err(TasksError::InvalidArgument(_))));
+    assert_eq!(store.writes, 0);
+    assert!(!store.inner.tasks()[0].is_completed());
+}
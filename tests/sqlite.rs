@@ -0,0 +1,32 @@
+#![cfg(feature = "sqlite")]
+
+mod common;
+
+use std::fs;
+
+use common::{scratch_dir, todos};
+use todos::{SqliteStore, TaskStore};
+
+#[test]
+fn single_task_changes_keep_tags_and_dependencies() {
+    let dir = scratch_dir("sqlite-rows");
+    let db = dir.join("t.sqlite");
+
+    todos(&db, &["add", "Write", "docs", "+docs", "+v2"]).unwrap();
+    todos(&db, &["add", "Release", "+v2"]).unwrap();
+    todos(&db, &["depends", "2", "on", "1"]).unwrap();
+    todos(&db, &["priority", "2", "H"]).unwrap();
+
+    let tasks = SqliteStore::open(db.clone()).unwrap().load().unwrap();
+    assert_eq!(tasks[0].tags(), ["docs", "v2"]);
+    assert_eq!(tasks[1].tags(), ["v2"]);
+    assert_eq!(tasks[1].dependencies(), [1]);
+
+    todos(&db, &["delete", "2"]).unwrap();
+
+    let tasks = SqliteStore::open(db).unwrap().load().unwrap();
+    assert_eq!(tasks.iter().map(|task| task.id()).collect::<Vec<_>>(), [1]);
+    assert_eq!(tasks[0].tags(), ["docs", "v2"]);
+
+    fs::remove_dir_all(dir).unwrap();
+}